@@ -0,0 +1,119 @@
+/*!
+ * Per-author counts of a file tracked in a Git repository.
+ *
+ * Notes:
+ *  - lines are attributed to the author of the last commit touching them
+ *  - the file is read as committed in HEAD, uncommitted changes are ignored
+ */
+
+use git2::Repository;
+use std::{collections::BTreeMap, path::Path};
+use text_analyzer::{FunctionResult, Report, analyze};
+
+// Author name -> report of the lines they last changed
+pub fn author_reports(path: &Path) -> FunctionResult<BTreeMap<String, Report>> {
+    let path = path.canonicalize()?;
+    let dir = path.parent().unwrap_or(&path);
+    let repo = Repository::discover(dir)
+        .map_err(|_| format!("{} is not in a Git repository", path.display()))?;
+    let workdir = repo
+        .workdir()
+        .ok_or("bare repositories are not supported")?;
+    let relative = path.strip_prefix(workdir.canonicalize()?)?;
+
+    let tree = repo.head()?.peel_to_tree()?;
+    let blob = tree.get_path(relative)?.to_object(&repo)?.peel_to_blob()?;
+    let content = String::from_utf8(blob.content().to_vec())?;
+    let lines: Vec<&str> = content.lines().collect();
+
+    // each author's lines, newline terminated, analyzed as one text
+    let mut texts: BTreeMap<String, String> = BTreeMap::new();
+    let blame = repo.blame_file(relative, None)?;
+    for hunk in blame.iter() {
+        let author = hunk
+            .final_signature()
+            .and_then(|signature| signature.name().ok().map(str::to_string))
+            .unwrap_or_else(|| "unknown".to_string());
+        let start = hunk.final_start_line().saturating_sub(1);
+        let text = texts.entry(author).or_default();
+        for line in lines.iter().skip(start).take(hunk.lines_in_hunk()) {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+
+    texts
+        .into_iter()
+        .map(|(author, text)| Ok((author, analyze(&text)?)))
+        .collect()
+}
+
+pub fn author_lines(reports: &BTreeMap<String, Report>) -> Vec<String> {
+    let mut lines = vec!["Authors:".to_string()];
+    for (author, report) in reports {
+        lines.push(format!(
+            "  {author}: {} words, {} lines",
+            report.word_count, report.line_count
+        ));
+    }
+    lines
+}
+
+// *********      Test     **********
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Oid, Signature};
+    use std::fs;
+
+    fn commit(repo: &Repository, author: &str, file: &str, content: &str) -> FunctionResult<Oid> {
+        fs::write(repo.workdir().ok_or("no workdir")?.join(file), content)?;
+        let mut index = repo.index()?;
+        index.add_path(Path::new(file))?;
+        index.write()?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let signature = Signature::now(author, &format!("{author}@example.com"))?;
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        Ok(repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "update",
+            &tree,
+            &parents,
+        )?)
+    }
+
+    #[test]
+    fn test_author_reports() -> FunctionResult<()> {
+        let dir = tempfile::tempdir()?;
+        let repo = Repository::init(dir.path())?;
+        commit(&repo, "Alice", "notes.txt", "lorem ipsum\ndolor\n")?;
+        commit(
+            &repo,
+            "Bob",
+            "notes.txt",
+            "lorem ipsum\ndolor\nsit amet consectetur\n",
+        )?;
+
+        let reports = author_reports(&dir.path().join("notes.txt"))?;
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports["Alice"].word_count, 3);
+        assert_eq!(reports["Alice"].line_count, 2);
+        assert_eq!(reports["Bob"].word_count, 3);
+        assert_eq!(reports["Bob"].line_count, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_author_reports_outside_repo() -> FunctionResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("notes.txt");
+        fs::write(&path, "lorem")?;
+
+        let error = author_reports(&path).unwrap_err().to_string();
+        assert!(error.contains("is not in a Git repository"));
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+/*!
+ * On-disk cache of reports, keyed by a SHA-256 of the analyzed content.
+ *
+ * Notes:
+ *  - One entry per line: `<key>\t<chars>\t<chars no spaces>\t<words>\t<lines>\t<unique lines>`
+ *  - A changed file hashes to a new key, so stale entries are never hit
+ *  - Averages are not stored, they are derived from the counts on load
+ */
+
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+use text_analyzer::{FunctionResult, Report};
+
+pub const DEFAULT_CACHE_FILE: &str = ".text-analyzer-cache";
+
+pub struct Cache {
+    path: PathBuf,
+    entries: HashMap<String, Report>,
+    pub hits: usize,
+}
+
+// `options` describes the settings changing the counts, they are part of the key
+pub fn cache_key(content: &str, options: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(content.as_bytes());
+    if !options.is_empty() {
+        hasher.update(b"\0options\0");
+        hasher.update(options.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+fn parse_entry(line: &str) -> Option<(String, Report)> {
+    let mut fields = line.split('\t');
+    let key = fields.next()?.to_string();
+    let mut report = Report {
+        char_count: fields.next()?.parse().ok()?,
+        chars_no_spaces: fields.next()?.parse().ok()?,
+        word_count: fields.next()?.parse().ok()?,
+        line_count: fields.next()?.parse().ok()?,
+        unique_line_count: fields.next()?.parse().ok()?,
+        ..Report::default()
+    };
+    report.update_averages();
+    Some((key, report))
+}
+
+impl Cache {
+    // A missing cache file is an empty cache, unreadable lines are ignored
+    pub fn load(path: &Path) -> FunctionResult<Cache> {
+        let entries = match fs::read_to_string(path) {
+            Ok(data) => data.lines().filter_map(parse_entry).collect(),
+            Err(e) if e.kind() == ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Cache {
+            path: path.to_path_buf(),
+            entries,
+            hits: 0,
+        })
+    }
+
+    pub fn get_or_compute(
+        &mut self,
+        key: &str,
+        compute: impl FnOnce() -> FunctionResult<Report>,
+    ) -> FunctionResult<Report> {
+        if let Some(report) = self.entries.get(key) {
+            self.hits += 1;
+            return Ok(report.clone());
+        }
+        let report = compute()?;
+        self.entries.insert(key.to_string(), report.clone());
+        Ok(report)
+    }
+
+    pub fn save(&self) -> FunctionResult<()> {
+        let mut data = String::new();
+        for (key, report) in &self.entries {
+            data.push_str(&format!(
+                "{key}\t{}\t{}\t{}\t{}\t{}\n",
+                report.char_count,
+                report.chars_no_spaces,
+                report.word_count,
+                report.line_count,
+                report.unique_line_count
+            ));
+        }
+        fs::write(&self.path, data)?;
+        Ok(())
+    }
+}
+
+// *********      Test     **********
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use text_analyzer::analyze;
+
+    #[test]
+    fn test_cache_hit() -> FunctionResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("cache");
+        let key = cache_key("lorem ipsum", "");
+
+        let mut cache = Cache::load(&path)?;
+        let report = cache.get_or_compute(&key, || analyze("lorem ipsum"))?;
+        assert_eq!(cache.hits, 0);
+        cache.save()?;
+
+        // second run with the same content is served from the file
+        let mut cache = Cache::load(&path)?;
+        let cached = cache.get_or_compute(&key, || Err("should not recompute".into()))?;
+        assert_eq!(cache.hits, 1);
+        assert_eq!(cached, report);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_stale_entry() -> FunctionResult<()> {
+        let dir = tempfile::tempdir()?;
+        let mut cache = Cache::load(&dir.path().join("cache"))?;
+        cache.get_or_compute(&cache_key("lorem", ""), || analyze("lorem"))?;
+
+        let changed = "lorem ipsum";
+        let report = cache.get_or_compute(&cache_key(changed, ""), || analyze(changed))?;
+        assert_eq!(cache.hits, 0);
+        assert_eq!(report.word_count, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_key() {
+        assert_eq!(cache_key("a", ""), cache_key("a", ""));
+        assert_ne!(cache_key("a", ""), cache_key("b", ""));
+        assert_ne!(cache_key("a;b", ""), cache_key("a;b", "record-sep=;"));
+    }
+}
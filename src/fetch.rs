@@ -0,0 +1,76 @@
+/*!
+ * Fetching of --url inputs, only built with the `http` feature.
+ *
+ * Notes:
+ *  - blocking requests, the analysis needs the whole body anyway
+ *  - non-2xx responses are errors, their body is not analyzed
+ */
+
+use std::time::Duration;
+use text_analyzer::FunctionResult;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+pub fn fetch_url(url: &str) -> FunctionResult<String> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(TIMEOUT)
+        .build()?;
+    let response = client
+        .get(url)
+        .send()
+        .map_err(|e| format!("Cannot fetch {url}: {e}"))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Cannot fetch {url}: server answered {status}").into());
+    }
+    Ok(response
+        .text()
+        .map_err(|e| format!("Cannot read the body of {url}: {e}"))?)
+}
+
+// *********      Test     **********
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    // Answers a single request with `status` and `body`, returns its URL
+    fn serve_once(status: &'static str, body: &'static str) -> FunctionResult<String> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}/page", listener.local_addr()?);
+        thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request);
+                let response = format!(
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        Ok(url)
+    }
+
+    #[test]
+    fn test_fetch_url() -> FunctionResult<()> {
+        let url = serve_once("200 OK", "lorem ipsum dolor")?;
+        assert_eq!(fetch_url(&url)?, "lorem ipsum dolor");
+        Ok(())
+    }
+
+    #[test]
+    fn test_fetch_url_errors() -> FunctionResult<()> {
+        let url = serve_once("404 Not Found", "missing")?;
+        let error = fetch_url(&url).unwrap_err().to_string();
+        assert!(error.contains("404"));
+
+        let error = fetch_url("http://127.0.0.1:1/").unwrap_err().to_string();
+        assert!(error.starts_with("Cannot fetch"));
+        Ok(())
+    }
+}
@@ -0,0 +1,2659 @@
+/*!
+ * Project: text-analyzer
+ * Description: Counting functions behind the text-analyzer CLI.
+ * Author: BigOuden
+ * GitHub: https://github.com/Big-Ouden/text-analyzer-rust
+ *
+ * Notes:
+ *  - `analyze` works on a whole string, `analyze_reader` on a stream
+ */
+
+// ********* Uses **********
+
+pub mod metrics;
+pub mod sketch;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet, hash_map::DefaultHasher},
+    hash::{Hash, Hasher},
+    io::{self, Read},
+    ops::{AddAssign, Range},
+    str,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+// ********* Constants **********
+
+/// Common English function words left out of vocabulary statistics.
+pub const STOP_WORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "am", "an", "and", "any", "are", "as", "at", "be",
+    "because", "been", "before", "being", "but", "by", "can", "could", "did", "do", "does", "for",
+    "from", "had", "has", "have", "he", "her", "here", "him", "his", "how", "i", "if", "in",
+    "into", "is", "it", "its", "just", "me", "more", "my", "no", "not", "now", "of", "on", "one",
+    "only", "or", "other", "our", "out", "over", "she", "so", "some", "than", "that", "the",
+    "their", "them", "then", "there", "these", "they", "this", "those", "to", "too", "up", "us",
+    "very", "was", "we", "were", "what", "when", "where", "which", "while", "who", "why", "will",
+    "with", "would", "you", "your",
+];
+
+/// Characters ending a sentence unless `--sentence-terminators` says otherwise.
+pub const DEFAULT_SENTENCE_TERMINATORS: &str = ".!?";
+
+/// Words whose period does not end a sentence, lowercase and without the
+/// final period; `--abbrev-file` adds more.
+pub const DEFAULT_ABBREVIATIONS: &[&str] = &[
+    "approx", "dr", "e.g", "fig", "i.e", "inc", "jr", "ltd", "mr", "mrs", "ms", "mt", "prof", "sr",
+    "st", "vs",
+];
+
+// *********    Type    **********
+pub type FunctionResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+// ********* Structures **********
+
+// Fields missing from a deserialized report, e.g. an older baseline, are 0;
+// fields it does not have are an error
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Report {
+    pub char_count: usize,
+    pub chars_no_spaces: usize,
+    pub word_count: usize,
+    pub line_count: usize,
+    pub unique_line_count: usize,
+    pub avg_words_per_line: f64,
+}
+
+impl Report {
+    // Derived metrics follow the counts, call after changing them
+    pub fn update_averages(&mut self) {
+        self.avg_words_per_line = average(self.word_count, self.line_count);
+    }
+}
+
+// Summing reports gives the totals of several inputs; unique lines are
+// summed too, so a line repeated across inputs counts once per input
+impl AddAssign<&Report> for Report {
+    fn add_assign(&mut self, other: &Report) {
+        self.char_count += other.char_count;
+        self.chars_no_spaces += other.chars_no_spaces;
+        self.word_count += other.word_count;
+        self.line_count += other.line_count;
+        self.unique_line_count += other.unique_line_count;
+        self.update_averages();
+    }
+}
+
+/// Time spent in each counter of an `Accumulator`, see `Accumulator::profiled`.
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    pub chars: Duration,
+    pub words: Duration,
+    pub lines: Duration,
+}
+
+impl AddAssign<&Profile> for Profile {
+    fn add_assign(&mut self, other: &Profile) {
+        self.chars += other.chars;
+        self.words += other.words;
+        self.lines += other.lines;
+    }
+}
+
+// Runs `count`, adding its duration to `slot` when profiling
+fn timed(slot: Option<&mut Duration>, count: impl FnOnce()) {
+    match slot {
+        Some(slot) => {
+            let start = Instant::now();
+            count();
+            *slot += start.elapsed();
+        }
+        None => count(),
+    }
+}
+
+/// Comment markers of a programming language, see `code_stats`.
+#[derive(Debug, Clone, Copy)]
+pub struct CommentSyntax {
+    pub line: &'static str,
+    pub block: Option<(&'static str, &'static str)>,
+}
+
+pub const RUST_COMMENTS: CommentSyntax = CommentSyntax {
+    line: "//",
+    block: Some(("/*", "*/")),
+};
+
+// Python, shell and other `#` languages
+pub const HASH_COMMENTS: CommentSyntax = CommentSyntax {
+    line: "#",
+    block: None,
+};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CodeStats {
+    pub code: usize,
+    pub comment: usize,
+    pub blank: usize,
+}
+
+// A fenced markdown code block; `language` is the first word of the info string
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeBlock {
+    pub language: String,
+    pub lines: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CodeFences {
+    pub blocks: Vec<CodeBlock>,
+    // 1-based line of a fence never closed, its block runs to the end of the text
+    pub unclosed: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListStats {
+    // `-`, `*` and `+` items
+    pub bullets: usize,
+    // `1.` and `1)` items
+    pub numbered: usize,
+    // 1 for a flat list, 0 without lists
+    pub max_depth: usize,
+}
+
+// Vocabulary richness, from `tokens` words (N) and their distinct forms (V); all 0 without words
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diversity {
+    // V / N, falls as texts get longer
+    pub type_token_ratio: f64,
+    // words seen once / N
+    pub hapax_ratio: f64,
+    // Guiraud's index V / √N, less sensitive to length
+    pub root_ttr: f64,
+}
+
+// Sentences by how they end; the fields add up to every sentence and the trailing fragment
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SentenceEndings {
+    pub period: usize,
+    pub question: usize,
+    pub exclamation: usize,
+    // "..." or "…"
+    pub ellipsis: usize,
+    // any other run of `terminators`, such as "。" or ".?."
+    pub other: usize,
+    // text after the last terminator, 0 or 1
+    pub unterminated: usize,
+}
+
+// Bytes the text takes in each encoding, without byte order mark
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EncodedSizes {
+    pub utf8: usize,
+    pub utf16: usize,
+    // representable characters only
+    pub latin1: usize,
+    // characters above U+00FF, which Latin-1 cannot hold
+    pub latin1_unrepresentable: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileEnding {
+    // whitespace-only lines after the last line with content
+    pub trailing_blank_lines: usize,
+    pub ends_with_newline: bool,
+}
+
+// Whitespace characters by kind, they add up to all whitespace of the text
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WhitespaceBreakdown {
+    pub spaces: usize,
+    pub tabs: usize,
+    pub newlines: usize,
+    // carriage returns, no-break spaces and any other Unicode whitespace
+    pub other: usize,
+}
+
+// Same hasher keys on every run, so equal lines always share a hash
+fn line_hash(line: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    line.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Incremental counter fed with consecutive chunks of a text.
+///
+/// Feeding a text in any number of chunks gives the same `Report` as
+/// `analyze` on the whole text. Only the current line and a 64-bit hash of
+/// each distinct line are kept, never the lines themselves; two different
+/// lines sharing a hash would count once, which is vanishingly unlikely.
+#[derive(Debug, Default)]
+pub struct Accumulator {
+    char_count: usize,
+    chars_no_spaces: usize,
+    word_count: usize,
+    newline_count: usize,
+    in_word: bool,
+    last_char: Option<char>,
+    line: String,
+    seen_lines: HashSet<u64>,
+    profile: Option<Profile>,
+}
+
+impl Accumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Same counts, but the time spent in each counter is recorded
+    pub fn profiled() -> Self {
+        Accumulator {
+            profile: Some(Profile::default()),
+            ..Self::default()
+        }
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    pub fn feed(&mut self, chunk: &str) {
+        let mut profile = self.profile.take();
+        let (chars, words, lines) = match &mut profile {
+            Some(p) => (Some(&mut p.chars), Some(&mut p.words), Some(&mut p.lines)),
+            None => (None, None, None),
+        };
+        timed(chars, || self.feed_chars(chunk));
+        timed(words, || self.feed_words(chunk));
+        timed(lines, || self.feed_lines(chunk));
+        self.profile = profile;
+    }
+
+    fn feed_chars(&mut self, chunk: &str) {
+        self.char_count += count_char(chunk);
+        self.chars_no_spaces += count_chars_no_spaces(chunk);
+    }
+
+    fn feed_words(&mut self, chunk: &str) {
+        for c in chunk.chars() {
+            if c.is_whitespace() {
+                self.in_word = false;
+            } else if !self.in_word {
+                self.in_word = true;
+                self.word_count += 1;
+            }
+        }
+    }
+
+    fn feed_lines(&mut self, chunk: &str) {
+        for c in chunk.chars() {
+            if c == '\n' {
+                self.newline_count += 1;
+                let line = std::mem::take(&mut self.line);
+                self.seen_lines
+                    .insert(line_hash(line.strip_suffix('\r').unwrap_or(&line)));
+            } else {
+                self.line.push(c);
+            }
+            self.last_char = Some(c);
+        }
+    }
+
+    pub fn report(&self) -> Report {
+        // like str::lines, an unterminated last line still counts
+        let unterminated = matches!(self.last_char, Some(c) if c != '\n');
+        let pending_is_new = unterminated && !self.seen_lines.contains(&line_hash(&self.line));
+        let mut report = Report {
+            char_count: self.char_count,
+            chars_no_spaces: self.chars_no_spaces,
+            word_count: self.word_count,
+            line_count: self.newline_count + usize::from(unterminated),
+            unique_line_count: self.seen_lines.len() + usize::from(pending_is_new),
+            ..Report::default()
+        };
+        report.update_averages();
+        report
+    }
+}
+
+// ********* Functions **********
+
+// The tokenizer behind every word-based metric
+pub fn tokens(text: &str) -> impl Iterator<Item = &str> {
+    text.split_whitespace()
+}
+
+pub fn count_words(text: &str) -> usize {
+    tokens(text).count()
+}
+
+// Extra word boundaries, off by default: "well-known" and "yes—no" are one word each
+pub const HYPHENS: &[char] = &['-', '\u{2010}'];
+pub const DASHES: &[char] = &['\u{2013}', '\u{2014}'];
+
+// `text` with every `boundaries` character turned into a space, for word-based counts
+pub fn split_words_at(text: &str, boundaries: &[char]) -> String {
+    text.replace(boundaries, " ")
+}
+
+pub fn whitespace_breakdown(text: &str) -> WhitespaceBreakdown {
+    let mut breakdown = WhitespaceBreakdown::default();
+    for c in text.chars().filter(|c| c.is_whitespace()) {
+        match c {
+            ' ' => breakdown.spaces += 1,
+            '\t' => breakdown.tabs += 1,
+            '\n' => breakdown.newlines += 1,
+            _ => breakdown.other += 1,
+        }
+    }
+    breakdown
+}
+
+pub fn count_char(text: &str) -> usize {
+    text.chars().count()
+}
+
+// "Characters (no spaces)" of word processors: whitespace is not counted
+pub fn count_chars_no_spaces(text: &str) -> usize {
+    text.chars().filter(|c| !c.is_whitespace()).count()
+}
+
+pub fn count_lines(text: &str) -> usize {
+    text.lines().count()
+}
+
+/// Classifies lines as blank, comment or code. A line with any code outside
+/// comments is code. Comment markers inside string literals are not
+/// recognized, and block comments do not nest.
+pub fn code_stats(text: &str, syntax: &CommentSyntax) -> CodeStats {
+    let mut stats = CodeStats::default();
+    let mut in_block = false;
+    for line in text.lines() {
+        let mut rest = line.trim();
+        if rest.is_empty() && !in_block {
+            stats.blank += 1;
+            continue;
+        }
+        let mut has_code = false;
+        loop {
+            if in_block {
+                let end = syntax.block.map_or("", |(_, end)| end);
+                match rest.find(end) {
+                    Some(index) => {
+                        in_block = false;
+                        rest = rest[index + end.len()..].trim_start();
+                    }
+                    None => break,
+                }
+            }
+            if rest.is_empty() || rest.starts_with(syntax.line) {
+                break;
+            }
+            if let Some((start, _)) = syntax.block.filter(|(start, _)| rest.starts_with(start)) {
+                in_block = true;
+                rest = &rest[start.len()..];
+                continue;
+            }
+            has_code = true;
+            // skip to the next comment marker, if any
+            let next = [Some(syntax.line), syntax.block.map(|(start, _)| start)]
+                .into_iter()
+                .flatten()
+                .filter_map(|marker| rest.find(marker))
+                .min();
+            match next {
+                Some(index) => rest = &rest[index..],
+                None => break,
+            }
+        }
+        if has_code {
+            stats.code += 1;
+        } else {
+            stats.comment += 1;
+        }
+    }
+    stats
+}
+
+const BLOCK_TAGS: &[&str] = &[
+    "p",
+    "div",
+    "br",
+    "li",
+    "ul",
+    "ol",
+    "tr",
+    "td",
+    "th",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "section",
+    "article",
+    "header",
+    "footer",
+    "blockquote",
+    "pre",
+    "table",
+];
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Visible text of an HTML document: tags are removed, block-level ones
+/// breaking the line, `script` and `style` contents are dropped and common
+/// entities decoded. The result is trimmed.
+pub fn strip_html(text: &str) -> String {
+    let mut visible = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        visible.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            // not a tag, keep the rest as text
+            rest = &rest[start..];
+            break;
+        };
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        if (name == "script" || name == "style") && !tag.starts_with('/') {
+            let close = format!("</{name}");
+            let lower = rest.to_ascii_lowercase();
+            rest = match lower.find(&close) {
+                Some(index) => rest[index..]
+                    .find('>')
+                    .map_or("", |gt| &rest[index + gt + 1..]),
+                None => "",
+            };
+        } else if BLOCK_TAGS.contains(&name.as_str()) {
+            visible.push('\n');
+        }
+    }
+    visible.push_str(rest);
+    decode_entities(&visible).trim().to_string()
+}
+
+static EMAIL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[\w.+-]+@[\w-]+(\.[\w-]+)*\.[A-Za-z]{2,}").expect("valid email pattern")
+});
+
+// 13 to 19 digits in one piece, or four groups of four set apart by single spaces or dashes
+static CARD_NUMBER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b([0-9]{13,19}|[0-9]{4}([ -][0-9]{4}){3}([ -][0-9]{1,3})?)\b")
+        .expect("valid card number pattern")
+});
+
+// Digit groups joined by dots or dashes; a space is only allowed after the country
+// code or the bracketed area code, so a list such as "100 200 300" is no number
+static PHONE_NUMBER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(\+[0-9]{1,3} ?)?(\([0-9]{1,4}\) ?)?[0-9]+([.-][0-9]+)*")
+        .expect("valid phone number pattern")
+});
+
+/// `text` with emails replaced by "[EMAIL]", card-like numbers (13 to 19
+/// digits) by "[CARD]" and phone-like numbers (10 to 12 digits) by "[PHONE]".
+/// Shorter numbers, dates among them, are kept.
+pub fn redact(text: &str) -> String {
+    let text = EMAIL.replace_all(text, "[EMAIL]");
+    let text = CARD_NUMBER.replace_all(&text, "[CARD]");
+    PHONE_NUMBER
+        .replace_all(&text, |found: &regex::Captures| {
+            let number = &found[0];
+            match number.chars().filter(char::is_ascii_digit).count() {
+                10..=12 => "[PHONE]".to_string(),
+                _ => number.to_string(),
+            }
+        })
+        .into_owned()
+}
+
+// `[text](url "title")` and `![alt](path)`, `[text][ref]` and `[text][]`
+static MARKDOWN_LINK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"!?\[([^\]]*)\](\([^)\s]*(\s+"[^"]*")?\)|\[[^\]]*\])"#)
+        .expect("valid markdown link pattern")
+});
+
+// `[ref]: http://... "title"` on a line of its own
+static MARKDOWN_LINK_DEFINITION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^ {0,3}\[[^\]]+\]:\s*\S+.*(\n|$)").expect("valid link definition pattern")
+});
+
+/// Markdown with links and images replaced by their text, so URLs and image
+/// paths are not counted as words. Reference definitions are removed.
+pub fn strip_markdown_links(text: &str) -> String {
+    let text = MARKDOWN_LINK_DEFINITION.replace_all(text, "");
+    MARKDOWN_LINK.replace_all(&text, "$1").into_owned()
+}
+
+// Runs of whitespace inside a line become one space, line ends are trimmed;
+// line breaks and a final newline are kept
+pub fn compact_whitespace(text: &str) -> String {
+    let mut compacted: Vec<String> = text
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect();
+    if text.ends_with('\n') {
+        compacted.push(String::new());
+    }
+    compacted.join("\n")
+}
+
+// Curly quotes, en and em dashes and "…" as word processors type them, replaced
+// by their ASCII spelling; the character count changes
+pub fn ascii_quotes(text: &str) -> String {
+    let mut ascii = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '“' | '”' | '„' => ascii.push('"'),
+            '‘' | '’' | '‚' => ascii.push('\''),
+            '–' => ascii.push('-'),
+            '—' => ascii.push_str("--"),
+            '…' => ascii.push_str("..."),
+            _ => ascii.push(c),
+        }
+    }
+    ascii
+}
+
+pub const DEFAULT_SQUEEZE_THRESHOLD: usize = 2;
+
+// Runs of the same character longer than `max` are cut to `max` ("sooo" -> "soo" for 2);
+// whitespace is left alone so line and word counts do not change
+pub fn squeeze_repeats(text: &str, max: usize) -> String {
+    let mut squeezed = String::with_capacity(text.len());
+    let mut previous = None;
+    let mut run = 0;
+    for c in text.chars() {
+        run = if previous == Some(c) { run + 1 } else { 1 };
+        previous = Some(c);
+        if run <= max || c.is_whitespace() {
+            squeezed.push(c);
+        }
+    }
+    squeezed
+}
+
+// Records are split like lines: no trailing empty record, none for empty text
+pub fn records<'a>(text: &'a str, separator: &'a str) -> impl Iterator<Item = &'a str> {
+    let text = text.strip_suffix(separator).unwrap_or(text);
+    text.split(separator).filter(move |_| !text.is_empty())
+}
+
+pub fn count_records(text: &str, separator: &str) -> usize {
+    records(text, separator).count()
+}
+
+// Number of different lines, optionally ignoring surrounding whitespace
+pub fn distinct_count<'a>(lines: impl Iterator<Item = &'a str>, trim: bool) -> usize {
+    lines
+        .map(|line| if trim { line.trim() } else { line })
+        .collect::<HashSet<&str>>()
+        .len()
+}
+
+pub fn unique_line_count(text: &str) -> usize {
+    distinct_count(text.lines(), false)
+}
+
+// Display width of a line, tabs advancing to the next tab stop
+pub fn line_width(line: &str, tab_width: usize) -> usize {
+    line.chars().fold(0, |width, c| match c {
+        '\t' if tab_width > 0 => (width / tab_width + 1) * tab_width,
+        '\t' => width,
+        _ => width + 1,
+    })
+}
+
+/// Lines wider than `max` as (1-based line number, width) pairs.
+pub fn long_lines(text: &str, max: usize, tab_width: usize) -> Vec<(usize, usize)> {
+    text.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line_width(line, tab_width)))
+        .filter(|&(_, width)| width > max)
+        .collect()
+}
+
+// Fence character and length of a ``` or ~~~ line, at most 3 spaces of indent
+fn fence(line: &str) -> Option<(char, usize, &str)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let c = trimmed.chars().next().filter(|&c| c == '`' || c == '~')?;
+    let length = trimmed.chars().take_while(|&x| x == c).count();
+    (length >= 3).then(|| (c, length, trimmed[length..].trim()))
+}
+
+/// Fenced code blocks of a markdown text. A block is closed by a fence of
+/// the same character, at least as long as the opening one.
+pub fn code_blocks(text: &str) -> CodeFences {
+    let mut fences = CodeFences::default();
+    // opening fence and its line number, language and lines so far
+    let mut open: Option<(char, usize, usize, CodeBlock)> = None;
+    for (index, line) in text.lines().enumerate() {
+        match (&mut open, fence(line)) {
+            (Some((c, length, _, _)), Some((close, close_length, info)))
+                if close == *c && close_length >= *length && info.is_empty() =>
+            {
+                if let Some((_, _, _, block)) = open.take() {
+                    fences.blocks.push(block);
+                }
+            }
+            (Some((_, _, _, block)), _) => block.lines += 1,
+            (None, Some((c, length, info))) => {
+                let language = info.split_whitespace().next().unwrap_or_default();
+                let block = CodeBlock {
+                    language: language.to_string(),
+                    lines: 0,
+                };
+                open = Some((c, length, index + 1, block));
+            }
+            (None, None) => {}
+        }
+    }
+    if let Some((_, _, line, block)) = open {
+        fences.blocks.push(block);
+        fences.unclosed = Some(line);
+    }
+    fences
+}
+
+// Lines outside fenced code blocks, the fences themselves excluded
+fn prose_lines(text: &str) -> Vec<&str> {
+    let mut prose = Vec::new();
+    let mut open: Option<(char, usize)> = None;
+    for line in text.lines() {
+        match (open, fence(line)) {
+            (Some((c, length)), Some((close, close_length, info)))
+                if close == c && close_length >= length && info.is_empty() =>
+            {
+                open = None
+            }
+            (Some(_), _) => {}
+            (None, Some((c, length, _))) => open = Some((c, length)),
+            (None, None) => prose.push(line),
+        }
+    }
+    prose
+}
+
+/// Markdown ATX headings per level, H1 first: lines starting at column 0
+/// with one to six `#` then a space. Code blocks are skipped.
+pub fn heading_counts(text: &str) -> [usize; 6] {
+    let mut counts = [0; 6];
+    for line in prose_lines(text) {
+        let level = line.chars().take_while(|&c| c == '#').count();
+        let rest = &line[level..];
+        if (1..=6).contains(&level) && (rest.is_empty() || rest.starts_with([' ', '\t'])) {
+            counts[level - 1] += 1;
+        }
+    }
+    counts
+}
+
+// Indent of a list item line and whether it is numbered
+fn list_item(line: &str) -> Option<(usize, bool)> {
+    let item = line.trim_start();
+    let indent = line.len() - item.len();
+    let digits = item.chars().take_while(char::is_ascii_digit).count();
+    let marker = if digits > 0 { digits + 1 } else { 1 };
+    let numbered = digits > 0 && item[digits..].starts_with(['.', ')']);
+    let bullet = digits == 0 && item.starts_with(['-', '*', '+']);
+    let spaced = item[marker.min(item.len())..].starts_with([' ', '\t']);
+    ((numbered || bullet) && spaced).then_some((indent, numbered))
+}
+
+/// Markdown list items outside code blocks. An item indented deeper than
+/// the one before it is nested one level below it.
+pub fn list_stats(text: &str) -> ListStats {
+    let mut stats = ListStats::default();
+    // indents of the items enclosing the current one
+    let mut parents: Vec<usize> = Vec::new();
+    for line in prose_lines(text) {
+        let Some((indent, numbered)) = list_item(line) else {
+            if !line.trim().is_empty() && !line.starts_with([' ', '\t']) {
+                parents.clear();
+            }
+            continue;
+        };
+        while parents.last().is_some_and(|&parent| parent >= indent) {
+            parents.pop();
+        }
+        parents.push(indent);
+        stats.max_depth = stats.max_depth.max(parents.len());
+        if numbered {
+            stats.numbered += 1;
+        } else {
+            stats.bullets += 1;
+        }
+    }
+    stats
+}
+
+// "text\n" is the convention: no trailing blank line and a final newline
+pub fn file_ending(text: &str) -> FileEnding {
+    FileEnding {
+        trailing_blank_lines: text
+            .lines()
+            .rev()
+            .take_while(|line| line.trim().is_empty())
+            .count(),
+        ends_with_newline: text.ends_with('\n'),
+    }
+}
+
+// Lines per width range: with bounds [40, 80] the ranges are 0-40, 41-80 and 81+.
+// Bounds are sorted and deduplicated first, the result has one count more than bounds.
+pub fn line_length_buckets(text: &str, bounds: &[usize], tab_width: usize) -> Vec<usize> {
+    let mut bounds = bounds.to_vec();
+    bounds.sort_unstable();
+    bounds.dedup();
+    let mut counts = vec![0; bounds.len() + 1];
+    for line in text.lines() {
+        let width = line_width(line, tab_width);
+        counts[bounds.partition_point(|&bound| bound < width)] += 1;
+    }
+    counts
+}
+
+// Emoji are whole grapheme clusters so that ZWJ sequences and flags count once
+fn is_emoji_char(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF // pictographs, emoticons, symbols, flags
+            | 0x2300..=0x23FF // misc technical (⌚, ⏰)
+            | 0x2600..=0x27BF // misc symbols and dingbats
+            | 0x2B00..=0x2BFF // arrows and shapes (⭐, ⬛)
+    )
+}
+
+fn emoji_clusters(text: &str) -> impl Iterator<Item = &str> {
+    text.graphemes(true)
+        .filter(|g| g.chars().next().is_some_and(is_emoji_char))
+}
+
+pub fn count_emoji(text: &str) -> usize {
+    emoji_clusters(text).count()
+}
+
+// Distinct emoji in order of first appearance
+pub fn distinct_emoji(text: &str) -> Vec<&str> {
+    let mut found: Vec<&str> = Vec::new();
+    for emoji in emoji_clusters(text) {
+        if !found.contains(&emoji) {
+            found.push(emoji);
+        }
+    }
+    found
+}
+
+// http(s) URLs among the tokens, without trailing sentence punctuation
+pub fn extract_urls(text: &str) -> Vec<&str> {
+    tokens(text)
+        .filter_map(|token| {
+            let start = token.find("http://").or_else(|| token.find("https://"))?;
+            let url = token[start..].trim_end_matches(|c: char| ".,;:!?)]}>\"'".contains(c));
+            url.contains("://").then_some(url)
+        })
+        .collect()
+}
+
+// Lowercased scheme and host, path without trailing slashes, query and
+// fragment untouched
+fn normalize_url(url: &str) -> String {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+    let host_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (host, rest) = rest.split_at(host_end);
+    let path_end = rest.find(['?', '#']).unwrap_or(rest.len());
+    let (path, tail) = rest.split_at(path_end);
+    format!(
+        "{}://{}{}{tail}",
+        scheme.to_lowercase(),
+        host.to_lowercase(),
+        path.trim_end_matches('/')
+    )
+}
+
+/// Distinct URLs after normalization, in order of first appearance.
+pub fn unique_urls(text: &str) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    for url in extract_urls(text).into_iter().map(normalize_url) {
+        if !found.contains(&url) {
+            found.push(url);
+        }
+    }
+    found
+}
+
+/// Blocks of text separated by blank lines, with trailing whitespace
+/// trimmed. Blank-only input has no paragraphs.
+pub fn paragraphs(text: &str) -> Vec<&str> {
+    let mut paragraphs = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut end = 0;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if line.trim().is_empty() {
+            if let Some(begin) = start.take() {
+                paragraphs.push(text[begin..end].trim_end());
+            }
+        } else {
+            start.get_or_insert(offset);
+            end = offset + line.len();
+        }
+        offset += line.len();
+    }
+    if let Some(begin) = start {
+        paragraphs.push(text[begin..end].trim_end());
+    }
+    paragraphs
+}
+
+// Greedy word wrap of each paragraph to `width` columns, paragraphs kept apart by a blank
+// line; a word longer than `width` gets a line of its own
+pub fn reflow(text: &str, width: usize) -> String {
+    let mut reflowed = Vec::new();
+    for paragraph in paragraphs(text) {
+        if !reflowed.is_empty() {
+            reflowed.push(String::new());
+        }
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+                reflowed.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        reflowed.push(line);
+    }
+    reflowed.iter().map(|line| format!("{line}\n")).collect()
+}
+
+pub fn paragraph_word_counts(text: &str) -> Vec<usize> {
+    paragraphs(text).iter().map(|p| count_words(p)).collect()
+}
+
+// Mean and max of paragraph word counts, the mean leaving out empty paragraphs
+pub fn paragraph_word_stats(counts: &[usize]) -> (f64, usize) {
+    let non_empty = counts.iter().filter(|&&words| words > 0).count();
+    let max = counts.iter().copied().max().unwrap_or(0);
+    (average(counts.iter().sum(), non_empty), max)
+}
+
+// `total / count`, 0 when there is nothing to divide by
+pub fn average(total: usize, count: usize) -> f64 {
+    if count == 0 {
+        return 0.0;
+    }
+    total as f64 / count as f64
+}
+
+// Seconds needed to read `words` words at `wpm` words per minute
+pub fn reading_time_secs(words: usize, wpm: u32) -> f64 {
+    if wpm == 0 {
+        return 0.0;
+    }
+    words as f64 * 60.0 / f64::from(wpm)
+}
+
+// Quotes and brackets that may close a sentence after its terminators: "Hi." She left.
+const SENTENCE_CLOSERS: &str = "\"')]}\u{201D}\u{2019}\u{BB}";
+
+// The period of "Dr. Smith" is no boundary, unless the text ends there
+fn after_abbreviation(before: &str, extra: &BTreeSet<String>) -> bool {
+    let word = before
+        .rsplit(char::is_whitespace)
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase();
+    DEFAULT_ABBREVIATIONS.contains(&word.as_str()) || extra.contains(&word)
+}
+
+// Runs of terminators followed by whitespace or the end of the text, so that
+// "3.14" or "e.g" do not end a sentence; closing quotes and brackets may come
+// in between and belong to the span. Scripts such as CJK do not put spaces
+// between sentences, so a run holding a non-ASCII terminator ("。") ends one
+// whatever follows. A period after one of DEFAULT_ABBREVIATIONS or
+// `abbreviations` is no boundary either.
+fn terminator_spans_except(
+    text: &str,
+    terminators: &str,
+    abbreviations: &BTreeSet<String>,
+) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((begin, c)) = chars.next() {
+        if !terminators.contains(c) {
+            continue;
+        }
+        let mut run_end = begin + c.len_utf8();
+        while let Some(&(index, c)) = chars.peek()
+            && terminators.contains(c)
+        {
+            run_end = index + c.len_utf8();
+            chars.next();
+        }
+        let mut end = run_end;
+        while let Some(&(index, c)) = chars.peek()
+            && SENTENCE_CLOSERS.contains(c)
+        {
+            end = index + c.len_utf8();
+            chars.next();
+        }
+
+        let run = &text[begin..run_end];
+        let boundary = match chars.peek() {
+            None => true,
+            Some(_) if !run.is_ascii() => true,
+            Some(&(_, next)) => {
+                next.is_whitespace()
+                    && !(run == "."
+                        && !text[end..].trim().is_empty()
+                        && after_abbreviation(&text[..begin], abbreviations))
+            }
+        };
+        if boundary {
+            spans.push(begin..end);
+        }
+    }
+    spans
+}
+
+// The terminators of each span, closing quotes and brackets left out
+fn terminator_runs<'a>(
+    text: &'a str,
+    terminators: &str,
+    abbreviations: &BTreeSet<String>,
+) -> Vec<&'a str> {
+    terminator_spans_except(text, terminators, abbreviations)
+        .into_iter()
+        .map(|span| text[span].trim_end_matches(|c| SENTENCE_CLOSERS.contains(c)))
+        .collect()
+}
+
+// Each sentence runs up to and including its terminators, trimmed; trailing
+// text without a terminator is not a sentence, as for `count_sentences`.
+// `abbreviations` add to DEFAULT_ABBREVIATIONS, see --abbrev-file
+pub fn sentences<'a>(
+    text: &'a str,
+    terminators: &str,
+    abbreviations: &BTreeSet<String>,
+) -> Vec<&'a str> {
+    let mut start = 0;
+    terminator_spans_except(text, terminators, abbreviations)
+        .into_iter()
+        .map(|span| {
+            let sentence = text[start..span.end].trim();
+            start = span.end;
+            sentence
+        })
+        .collect()
+}
+
+// The `k` sentences with the most words, ties in order of appearance
+pub fn long_sentences<'a>(
+    text: &'a str,
+    k: usize,
+    terminators: &str,
+    abbreviations: &BTreeSet<String>,
+) -> Vec<(&'a str, usize)> {
+    let mut ranked: Vec<(&str, usize)> = sentences(text, terminators, abbreviations)
+        .into_iter()
+        .map(|sentence| (sentence, count_words(sentence)))
+        .collect();
+    ranked.sort_by_key(|&(_, words)| std::cmp::Reverse(words));
+    ranked.truncate(k);
+    ranked
+}
+
+// Word count of each sentence, sentences without words are left out
+pub fn words_per_sentence(
+    text: &str,
+    terminators: &str,
+    abbreviations: &BTreeSet<String>,
+) -> Vec<usize> {
+    sentences(text, terminators, abbreviations)
+        .into_iter()
+        .map(count_words)
+        .filter(|&words| words > 0)
+        .collect()
+}
+
+// Counts of `values` in buckets of `width` starting at 1: (1..=width, width+1..=2*width, ...),
+// every bucket up to the largest value is listed, empty ones included
+pub fn bucket_counts(values: &[usize], width: usize) -> Vec<(usize, usize)> {
+    let width = width.max(1);
+    let Some(&max) = values.iter().max() else {
+        return Vec::new();
+    };
+    let mut buckets: Vec<(usize, usize)> = (0..max.div_ceil(width))
+        .map(|index| (index * width + 1, 0))
+        .collect();
+    for &value in values.iter().filter(|&&value| value > 0) {
+        buckets[(value - 1) / width].1 += 1;
+    }
+    buckets
+}
+
+// A form of "to be", an optional adverb, then a regular (-ed) or common irregular participle
+static PASSIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\b(am|is|are|was|were|be|been|being)\s+(\w+ly\s+)?(\w+ed|born|brought|built|bought|caught|chosen|done|driven|eaten|found|given|held|kept|known|left|made|paid|put|said|seen|sent|set|shown|sold|spoken|taken|taught|thought|thrown|told|written)\b",
+    )
+    .expect("valid passive voice pattern")
+});
+
+/// Sentences that look passive: "to be" followed by a past participle. This
+/// is a heuristic: adjectives ending in -ed ("was tired") are false positives
+/// and irregular participles outside a short list are missed.
+pub fn passive_sentences<'a>(
+    text: &'a str,
+    terminators: &str,
+    abbreviations: &BTreeSet<String>,
+) -> Vec<&'a str> {
+    sentences(text, terminators, abbreviations)
+        .into_iter()
+        .filter(|sentence| PASSIVE.is_match(sentence))
+        .collect()
+}
+
+// Whitespace runs collapsed and case folded, so retyped copies compare equal
+fn sentence_key(sentence: &str) -> String {
+    tokens(sentence)
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+pub fn unique_sentence_count(
+    text: &str,
+    terminators: &str,
+    abbreviations: &BTreeSet<String>,
+) -> usize {
+    sentences(text, terminators, abbreviations)
+        .into_iter()
+        .map(sentence_key)
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+// Sentences seen more than once, as first written, with their number of occurrences
+pub fn duplicate_sentences<'a>(
+    text: &'a str,
+    terminators: &str,
+    abbreviations: &BTreeSet<String>,
+) -> Vec<(&'a str, usize)> {
+    let mut seen: Vec<(String, &str, usize)> = Vec::new();
+    for sentence in sentences(text, terminators, abbreviations) {
+        let key = sentence_key(sentence);
+        match seen.iter_mut().find(|(seen_key, _, _)| *seen_key == key) {
+            Some(entry) => entry.2 += 1,
+            None => seen.push((key, sentence, 1)),
+        }
+    }
+    seen.into_iter()
+        .filter(|&(_, _, count)| count > 1)
+        .map(|(_, sentence, count)| (sentence, count))
+        .collect()
+}
+
+pub const DEFAULT_FRAGMENT_WORDS: usize = 3;
+
+// Sentences of fewer than `min_words` words, likely fragments ("Yes.", "Not really.")
+pub fn sentence_fragments<'a>(
+    text: &'a str,
+    min_words: usize,
+    terminators: &str,
+    abbreviations: &BTreeSet<String>,
+) -> Vec<&'a str> {
+    sentences(text, terminators, abbreviations)
+        .into_iter()
+        .filter(|sentence| count_words(sentence) < min_words)
+        .collect()
+}
+
+// First character of a sentence after any opening quotes and brackets
+fn sentence_start(sentence: &str) -> Option<char> {
+    sentence
+        .chars()
+        .find(|c| !"\"'([{\u{201C}\u{2018}\u{AB}".contains(*c))
+}
+
+pub fn capitalized_sentence_count(
+    text: &str,
+    terminators: &str,
+    abbreviations: &BTreeSet<String>,
+) -> usize {
+    sentences(text, terminators, abbreviations)
+        .into_iter()
+        .filter(|sentence| sentence_start(sentence).is_some_and(char::is_uppercase))
+        .count()
+}
+
+/// 1-based positions of the sentences starting with a lowercase letter,
+/// leading quotes and brackets skipped ("(yes) ..." starts with 'y').
+/// Sentences starting with a digit or symbol are neither.
+pub fn lowercase_sentences(
+    text: &str,
+    terminators: &str,
+    abbreviations: &BTreeSet<String>,
+) -> Vec<usize> {
+    sentences(text, terminators, abbreviations)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, sentence)| sentence_start(sentence).is_some_and(char::is_lowercase))
+        .map(|(index, _)| index + 1)
+        .collect()
+}
+
+/// Sentences ending with `?`. A mixed ending such as "?!" or "!?" is a
+/// question: any `?` in the final punctuation wins. Sentences are split as
+/// `count_sentences` splits them.
+pub fn count_questions(text: &str, terminators: &str, abbreviations: &BTreeSet<String>) -> usize {
+    terminator_runs(text, terminators, abbreviations)
+        .iter()
+        .filter(|run| run.contains('?'))
+        .count()
+}
+
+/// Sentences ending with `!` and no `?`.
+pub fn count_exclamations(
+    text: &str,
+    terminators: &str,
+    abbreviations: &BTreeSet<String>,
+) -> usize {
+    terminator_runs(text, terminators, abbreviations)
+        .iter()
+        .filter(|run| run.contains('!') && !run.contains('?'))
+        .count()
+}
+
+/// Every other sentence: periods only (including "...") with the default
+/// terminators, so the three counts add up to `count_sentences`.
+pub fn count_statements(text: &str, terminators: &str, abbreviations: &BTreeSet<String>) -> usize {
+    terminator_runs(text, terminators, abbreviations)
+        .iter()
+        .filter(|run| !run.contains(['?', '!']))
+        .count()
+}
+
+// Sentences ended by any of the `terminators` characters, not splitting after
+// DEFAULT_ABBREVIATIONS and the `abbreviations`, given as they are
+pub fn count_sentences(text: &str, terminators: &str, abbreviations: &BTreeSet<String>) -> usize {
+    terminator_spans_except(text, terminators, abbreviations).len()
+}
+
+// Split as `count_sentences` splits; a run holding "?" is a question even when it also
+// holds "!", a run of periods only is an ellipsis from two of them, as is "…"
+pub fn sentence_ending_breakdown(
+    text: &str,
+    terminators: &str,
+    abbreviations: &BTreeSet<String>,
+) -> SentenceEndings {
+    let mut endings = SentenceEndings::default();
+    let spans = terminator_spans_except(text, terminators, abbreviations);
+    for span in &spans {
+        let run = text[span.clone()].trim_end_matches(|c| SENTENCE_CLOSERS.contains(c));
+        if run.contains('?') {
+            endings.question += 1;
+        } else if run.contains('!') {
+            endings.exclamation += 1;
+        } else if run == "…" || (run.len() > 1 && run.chars().all(|c| c == '.')) {
+            endings.ellipsis += 1;
+        } else if run == "." {
+            endings.period += 1;
+        } else {
+            endings.other += 1;
+        }
+    }
+    let rest = &text[spans.last().map_or(0, |span| span.end)..];
+    if count_words(rest) > 0 {
+        endings.unterminated = 1;
+    }
+    endings
+}
+
+// Script of a letter by its code point block, non-letters have none
+fn script_of(c: char) -> Option<&'static str> {
+    if !c.is_alphabetic() {
+        return None;
+    }
+    let script = match c as u32 {
+        0x0000..=0x024F | 0x1E00..=0x1EFF => "Latin",
+        0x0370..=0x03FF | 0x1F00..=0x1FFF => "Greek",
+        0x0400..=0x052F => "Cyrillic",
+        0x0530..=0x058F => "Armenian",
+        0x0590..=0x05FF => "Hebrew",
+        0x0600..=0x06FF | 0x0750..=0x077F => "Arabic",
+        0x0900..=0x097F => "Devanagari",
+        0x0E00..=0x0E7F => "Thai",
+        0x10A0..=0x10FF => "Georgian",
+        0x1100..=0x11FF | 0xAC00..=0xD7AF => "Hangul",
+        0x3040..=0x309F => "Hiragana",
+        0x30A0..=0x30FF => "Katakana",
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0x20000..=0x2FFFF => "CJK",
+        _ => "Other",
+    };
+    Some(script)
+}
+
+/// Number of letters per Unicode script, digits, punctuation and spaces are not counted.
+pub fn script_breakdown(text: &str) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for script in text.chars().filter_map(script_of) {
+        *counts.entry(script.to_string()).or_insert(0) += 1;
+    }
+    counts
+}
+
+// Numbers found in a text, and the number-like tokens that did not parse
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Numbers<'a> {
+    pub values: Vec<f64>,
+    pub malformed: Vec<&'a str>,
+}
+
+// "1,234.5" groups digits by three with commas, which are dropped
+fn without_thousands(number: &str) -> Option<String> {
+    let (integer, fraction) = number.split_once('.').unwrap_or((number, ""));
+    let integer = integer.trim_start_matches(['-', '+']);
+    let mut groups = integer.split(',');
+    let first = groups.next()?;
+    let valid = (1..=3).contains(&first.len())
+        && groups.all(|group| group.len() == 3)
+        && !fraction.contains(',');
+    valid.then(|| number.replace(',', ""))
+}
+
+/// Numeric tokens (`10`, `-2.5`, `$30`, `1,200`), surrounding symbols and
+/// punctuation ignored. Number-like tokens that do not parse, such as
+/// "1.2.3", are returned as malformed.
+pub fn numbers(text: &str) -> Numbers<'_> {
+    let mut found = Numbers::default();
+    for token in tokens(text) {
+        let number = token
+            .trim_start_matches(|c: char| !(c.is_ascii_digit() || "+-.".contains(c)))
+            .trim_end_matches(|c: char| !c.is_ascii_digit());
+        let numeric = number.chars().any(|c| c.is_ascii_digit())
+            && number
+                .chars()
+                .all(|c| c.is_ascii_digit() || "+-.,".contains(c));
+        if !numeric {
+            continue;
+        }
+        let parsed = if number.contains(',') {
+            without_thousands(number).and_then(|plain| plain.parse().ok())
+        } else {
+            number.parse().ok()
+        };
+        match parsed {
+            Some(value) => found.values.push(value),
+            None => found.malformed.push(token),
+        }
+    }
+    found
+}
+
+pub fn sum_numbers(text: &str) -> f64 {
+    numbers(text).values.iter().sum()
+}
+
+// Below this many letters a language guess is noise
+pub const MIN_LANGUAGE_LETTERS: usize = 20;
+
+/// ISO 639-3 code of the most likely language ("eng", "fra", ...) and the
+/// confidence of the guess, from 0 to 1. None when the text is too short.
+pub fn detect_language(text: &str) -> Option<(&'static str, f64)> {
+    if text.chars().filter(|c| c.is_alphabetic()).count() < MIN_LANGUAGE_LETTERS {
+        return None;
+    }
+    whatlang::detect(text).map(|info| (info.lang().code(), info.confidence()))
+}
+
+/// Words whose letters come from more than one script, such as "раypal"
+/// with Cyrillic "р" and "а": likely homoglyph spoofing. Kanji mixed with
+/// kana is normal Japanese and not reported. Each word is listed once.
+pub fn mixed_script_words(text: &str) -> Vec<String> {
+    let mut suspicious: Vec<String> = Vec::new();
+    for word in tokens(text).filter_map(|word| normalize_word(word, false)) {
+        let scripts: BTreeSet<&str> = word
+            .chars()
+            .filter_map(script_of)
+            .map(|script| match script {
+                "Hiragana" | "Katakana" => "CJK",
+                other => other,
+            })
+            .collect();
+        if scripts.len() > 1 && !suspicious.contains(&word) {
+            suspicious.push(word);
+        }
+    }
+    suspicious
+}
+
+// First letter of each word, lowercased, skipping leading non-letters ("(apple" -> 'a')
+pub fn initial_letter_frequencies(text: &str) -> BTreeMap<char, usize> {
+    let mut frequencies = BTreeMap::new();
+    for word in tokens(text) {
+        if let Some(letter) = word.chars().find(|c| c.is_alphabetic()) {
+            let letter = letter.to_lowercase().next().unwrap_or(letter);
+            *frequencies.entry(letter).or_insert(0) += 1;
+        }
+    }
+    frequencies
+}
+
+// First non-whitespace character of each line, letters lowercased, anything else
+// kept as is ('-' and '*' for list items, '#' for headings, digits); blank lines skipped
+pub fn line_initial_frequencies(text: &str) -> BTreeMap<char, usize> {
+    let mut frequencies = BTreeMap::new();
+    for line in text.lines() {
+        if let Some(c) = line.trim_start().chars().next() {
+            let c = c.to_lowercase().next().unwrap_or(c);
+            *frequencies.entry(c).or_insert(0) += 1;
+        }
+    }
+    frequencies
+}
+
+// Most frequent initial, the alphabetically first one on ties
+pub fn most_common_initial(frequencies: &BTreeMap<char, usize>) -> Option<char> {
+    let mut best: Option<(char, usize)> = None;
+    for (&letter, &count) in frequencies {
+        if best.is_none_or(|(_, best_count)| count > best_count) {
+            best = Some((letter, count));
+        }
+    }
+    best.map(|(letter, _)| letter)
+}
+
+// Token used for vocabulary: surrounding punctuation trimmed, optionally lowercased
+pub fn normalize_word(word: &str, ignore_case: bool) -> Option<String> {
+    let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+    if word.is_empty() {
+        None
+    } else if ignore_case {
+        Some(word.to_lowercase())
+    } else {
+        Some(word.to_string())
+    }
+}
+
+pub fn is_stop_word(word: &str) -> bool {
+    STOP_WORDS.contains(&word.to_lowercase().as_str())
+}
+
+// Percentage of words that are stop words, 0 for a text without words
+pub fn stopword_ratio(text: &str) -> f64 {
+    let words: Vec<String> = tokens(text)
+        .filter_map(|word| normalize_word(word, true))
+        .collect();
+    let stop = words.iter().filter(|word| is_stop_word(word)).count();
+    average(stop * 100, words.len())
+}
+
+pub fn encoded_sizes(text: &str) -> EncodedSizes {
+    let chars = text.chars().count();
+    let unrepresentable = text.chars().filter(|&c| u32::from(c) > 0xFF).count();
+    EncodedSizes {
+        utf8: text.len(),
+        utf16: text.encode_utf16().count() * 2,
+        latin1: chars - unrepresentable,
+        latin1_unrepresentable: unrepresentable,
+    }
+}
+
+// Percentage of characters that are ASCII digits, 0 for an empty text
+pub fn digit_ratio(text: &str) -> f64 {
+    let digits = text.chars().filter(char::is_ascii_digit).count();
+    average(digits * 100, text.chars().count())
+}
+
+// Occurrences of each normalized word
+pub fn word_frequencies(text: &str, ignore_case: bool) -> BTreeMap<String, usize> {
+    let mut frequencies = BTreeMap::new();
+    for word in tokens(text).filter_map(|word| normalize_word(word, ignore_case)) {
+        *frequencies.entry(word).or_insert(0) += 1;
+    }
+    frequencies
+}
+
+// Occurrences of each run of `n` consecutive normalized words, joined by a space
+pub fn ngram_frequencies(text: &str, n: usize, ignore_case: bool) -> BTreeMap<String, usize> {
+    let words: Vec<String> = tokens(text)
+        .filter_map(|word| normalize_word(word, ignore_case))
+        .collect();
+    let mut frequencies = BTreeMap::new();
+    if n == 0 {
+        return frequencies;
+    }
+    for ngram in words.windows(n) {
+        *frequencies.entry(ngram.join(" ")).or_insert(0) += 1;
+    }
+    frequencies
+}
+
+// N-grams seen more than once, most frequent first then alphabetically
+pub fn repeated_ngrams(text: &str, n: usize, ignore_case: bool) -> Vec<(String, usize)> {
+    let mut repeated: Vec<(String, usize)> = ngram_frequencies(text, n, ignore_case)
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .collect();
+    repeated.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    repeated
+}
+
+// Lowercased words starting with `prefix`, case ignored, and their occurrences;
+// an empty prefix matches every word
+pub fn words_starting_with(text: &str, prefix: &str) -> BTreeMap<String, usize> {
+    let prefix = prefix.to_lowercase();
+    let mut words = word_frequencies(text, true);
+    words.retain(|word, _| word.starts_with(&prefix));
+    words
+}
+
+// Words occurring exactly once, alphabetically, "Word" and "word" merged with `ignore_case`
+pub fn hapax_legomena(text: &str, ignore_case: bool) -> Vec<String> {
+    word_frequencies(text, ignore_case)
+        .into_iter()
+        .filter(|(_, count)| *count == 1)
+        .map(|(word, _)| word)
+        .collect()
+}
+
+pub fn lexical_diversity(text: &str, ignore_case: bool) -> Diversity {
+    let frequencies = word_frequencies(text, ignore_case);
+    let tokens: usize = frequencies.values().sum();
+    if tokens == 0 {
+        return Diversity::default();
+    }
+    let types = frequencies.len() as f64;
+    let hapaxes = frequencies.values().filter(|&&count| count == 1).count() as f64;
+    Diversity {
+        type_token_ratio: types / tokens as f64,
+        hapax_ratio: hapaxes / tokens as f64,
+        root_ttr: types / (tokens as f64).sqrt(),
+    }
+}
+
+// Short function words a title may keep in lower case, except as its first word
+const TITLE_CASE_EXCEPTIONS: &[&str] = &[
+    "a", "an", "and", "as", "at", "but", "by", "for", "in", "nor", "of", "on", "or", "the", "to",
+];
+
+// 1-based numbers of the lines whose words all start with a capital letter, the
+// TITLE_CASE_EXCEPTIONS aside; words without letters are ignored, and a line needs
+// at least one word with letters
+pub fn title_case_lines(text: &str) -> Vec<usize> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let words: Vec<&str> = tokens(line)
+                .map(|word| word.trim_start_matches(|c: char| !c.is_alphabetic()))
+                .filter(|word| !word.is_empty())
+                .collect();
+            !words.is_empty()
+                && words.iter().enumerate().all(|(i, word)| {
+                    word.starts_with(char::is_uppercase)
+                        || (i > 0 && TITLE_CASE_EXCEPTIONS.contains(word))
+                })
+        })
+        .map(|(index, _)| index + 1)
+        .collect()
+}
+
+/// 1-based numbers of the lines where a word, case folded, appears more
+/// than `n` times.
+pub fn lines_with_repeated_words(text: &str, n: usize) -> Vec<usize> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            word_frequencies(line, true)
+                .values()
+                .any(|&count| count > n)
+        })
+        .map(|(index, _)| index + 1)
+        .collect()
+}
+
+/// Non stop-words seen strictly more than `threshold` times, most frequent
+/// first and alphabetically on ties.
+pub fn overused_words(text: &str, threshold: usize, ignore_case: bool) -> Vec<(String, usize)> {
+    let mut overused: Vec<(String, usize)> = word_frequencies(text, ignore_case)
+        .into_iter()
+        .filter(|(word, count)| *count > threshold && !is_stop_word(word))
+        .collect();
+    overused.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    overused
+}
+
+const CONTRACTION_SUFFIXES: &[&str] = &["n't", "'re", "'ve", "'ll", "'d", "'m"];
+
+// Words whose "'s" is "is", "has" or "us"; after any other word it marks a possessive
+const S_CONTRACTION_WORDS: &[&str] = &[
+    "it", "he", "she", "that", "what", "there", "here", "who", "where", "when", "how", "let",
+];
+
+fn is_contraction(word: &str) -> bool {
+    let word = word.to_lowercase().replace('\u{2019}', "'");
+    if let Some(stem) = word.strip_suffix("'s") {
+        return S_CONTRACTION_WORDS.contains(&stem);
+    }
+    CONTRACTION_SUFFIXES
+        .iter()
+        .any(|suffix| word.len() > suffix.len() && word.ends_with(suffix))
+}
+
+/// English contractions such as "don't", "you'll" or "it's". An "'s" ending
+/// only counts after a pronoun-like word ("it's", "that's"), otherwise it is
+/// taken as a possessive ("dog's").
+pub fn count_contractions(text: &str) -> usize {
+    tokens(text)
+        .filter_map(|word| normalize_word(word, false))
+        .filter(|word| is_contraction(word))
+        .count()
+}
+
+/// Words between double quotes, straight (`"..."`) or curly (`“...”`). A
+/// quote left open at the end of the text is ignored, its words do not count.
+pub fn words_in_quotes(text: &str) -> usize {
+    let mut words = 0;
+    // byte offset after the opening quote, and the quote closing it
+    let mut open: Option<(usize, char)> = None;
+    for (i, c) in text.char_indices() {
+        match (open, c) {
+            (Some((start, close)), _) if c == close => {
+                words += count_words(&text[start..i]);
+                open = None;
+            }
+            (None, '"') => open = Some((i + c.len_utf8(), '"')),
+            (None, '\u{201C}') => open = Some((i + c.len_utf8(), '\u{201D}')),
+            _ => {}
+        }
+    }
+    words
+}
+
+// At least two uppercase letters, starting with one, digits allowed after it ("MP3", "B2B")
+fn is_acronym(word: &str) -> bool {
+    word.chars().next().is_some_and(|c| c.is_uppercase())
+        && word.chars().all(|c| c.is_uppercase() || c.is_ascii_digit())
+        && word.chars().filter(|c| c.is_uppercase()).count() >= 2
+}
+
+/// All-uppercase words with their frequency, most frequent first and
+/// alphabetically on ties. Single letters such as "I" or "A" are not acronyms.
+pub fn count_acronyms(text: &str) -> Vec<(String, usize)> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for word in tokens(text).filter_map(|word| normalize_word(word, false)) {
+        if is_acronym(&word) {
+            *counts.entry(word).or_insert(0) += 1;
+        }
+    }
+    let mut acronyms: Vec<(String, usize)> = counts.into_iter().collect();
+    acronyms.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    acronyms
+}
+
+/// Percentage of words with two letters or more whose letters are all
+/// uppercase, 0 without such words. Single letters ("I", "A") are left out of
+/// both counts; acronyms cannot be told from shouting and count as capitals.
+pub fn caps_ratio(text: &str) -> f64 {
+    let words: Vec<String> = tokens(text)
+        .filter_map(|word| normalize_word(word, false))
+        .filter(|word| word.chars().filter(|c| c.is_alphabetic()).count() >= 2)
+        .collect();
+    let caps = words
+        .iter()
+        .filter(|word| word.chars().all(|c| !c.is_alphabetic() || c.is_uppercase()))
+        .count();
+    average(caps * 100, words.len())
+}
+
+pub fn vocabulary(text: &str, ignore_case: bool) -> BTreeSet<String> {
+    tokens(text)
+        .filter_map(|word| normalize_word(word, ignore_case))
+        .collect()
+}
+
+/// Words of `new` missing from `old` (added) and words of `old` missing
+/// from `new` (removed), both sorted.
+pub fn diff_words(old: &str, new: &str, ignore_case: bool) -> (Vec<String>, Vec<String>) {
+    let old = vocabulary(old, ignore_case);
+    let new = vocabulary(new, ignore_case);
+    let added = new.difference(&old).cloned().collect();
+    let removed = old.difference(&new).cloned().collect();
+    (added, removed)
+}
+
+pub fn analyze(text: &str) -> FunctionResult<Report> {
+    let mut report: Report = Report {
+        char_count: count_char(text),
+        chars_no_spaces: count_chars_no_spaces(text),
+        word_count: count_words(text),
+        line_count: count_lines(text),
+        unique_line_count: unique_line_count(text),
+        ..Report::default()
+    };
+    report.update_averages();
+    Ok(report)
+}
+
+/// Analyze a stream without holding all of it in memory.
+///
+/// Fails with `InvalidData` if the stream is not valid UTF-8.
+pub fn analyze_reader<R: Read>(mut reader: R) -> io::Result<Report> {
+    let mut accumulator = Accumulator::new();
+    let mut buffer = [0u8; 8192];
+    // bytes of a character split across two reads
+    let mut pending: Vec<u8> = Vec::new();
+
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        pending.extend_from_slice(&buffer[..read]);
+
+        let valid = match str::from_utf8(&pending) {
+            Ok(text) => text.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        };
+        let text = str::from_utf8(&pending[..valid])
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        accumulator.feed(text);
+        pending.drain(..valid);
+    }
+
+    if !pending.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "stream ended in the middle of a UTF-8 character",
+        ));
+    }
+    Ok(accumulator.report())
+}
+
+// *********      Test     **********
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_count_words() {
+        assert_eq!(count_words("lorem ipsum"), 2);
+        assert_eq!(count_words(""), 0);
+        assert_eq!(count_words("lorem ipsum^#~"), 2);
+        assert_eq!(count_words("lorem ipsu\n coucou"), 3);
+        assert_eq!(count_words("lorem   ipsum   "), 2);
+    }
+
+    #[test]
+    fn test_whitespace_breakdown() {
+        assert_eq!(
+            whitespace_breakdown("a\tb c\n"),
+            WhitespaceBreakdown {
+                spaces: 1,
+                tabs: 1,
+                newlines: 1,
+                other: 0
+            }
+        );
+
+        let text = "x\r\n\u{a0}y  \t\n";
+        let b = whitespace_breakdown(text);
+        assert_eq!((b.spaces, b.tabs, b.newlines, b.other), (2, 1, 2, 2));
+        let total = count_char(text) - count_chars_no_spaces(text);
+        assert_eq!(b.spaces + b.tabs + b.newlines + b.other, total);
+    }
+
+    #[test]
+    fn test_split_words_at() {
+        assert_eq!(count_words("well-known"), 1);
+        assert_eq!(count_words(&split_words_at("well-known", HYPHENS)), 2);
+        assert_eq!(
+            count_words(&split_words_at("state-of-the-art—or not", HYPHENS)),
+            5
+        );
+        assert_eq!(count_words(&split_words_at("yes—no", DASHES)), 2);
+        assert_eq!(split_words_at("a – b", &[]), "a – b");
+    }
+
+    #[test]
+    fn test_tokens() -> FunctionResult<()> {
+        assert_eq!(
+            tokens(" lorem\tipsum\n dolor ").collect::<Vec<_>>(),
+            ["lorem", "ipsum", "dolor"]
+        );
+        for text in ["", "lorem ipsum^#~", "a  b\n\nc 😊"] {
+            assert_eq!(tokens(text).count(), analyze(text)?.word_count);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_char() {
+        assert_eq!(count_char(""), 0);
+        assert_eq!(count_char("abc"), 3);
+        assert_eq!(count_char(" abc "), 5);
+        assert_eq!(count_char("a.b.c"), 5);
+        assert_eq!(count_char("😊"), 1);
+    }
+
+    #[test]
+    fn test_count_chars_no_spaces() {
+        assert_eq!(count_chars_no_spaces("a b c"), 3);
+        assert_eq!(count_char("a b c"), 5);
+        assert_eq!(count_chars_no_spaces(" \t\n"), 0);
+        assert_eq!(count_chars_no_spaces("😊 b"), 2);
+    }
+
+    #[test]
+    fn test_count_lines() {
+        assert_eq!(count_lines("1\n2\n2\n4"), 4);
+        assert_eq!(count_lines("1"), 1);
+        assert_eq!(count_lines(""), 0);
+    }
+
+    #[test]
+    fn test_count_records() {
+        assert_eq!(count_records("a;b;c", ";"), 3);
+        assert_eq!(count_records("a;b;c;", ";"), 3);
+        assert_eq!(count_records("a\0b", "\0"), 2);
+        assert_eq!(count_records("", ";"), 0);
+    }
+
+    #[test]
+    fn test_unique_line_count() -> FunctionResult<()> {
+        assert_eq!(unique_line_count("a\na\nb"), 2);
+        assert_eq!(analyze("a\na\nb")?.line_count, 3);
+        assert_eq!(unique_line_count(""), 0);
+        assert_eq!(distinct_count("a\n a \nb".lines(), false), 3);
+        assert_eq!(distinct_count("a\n a \nb".lines(), true), 2);
+        assert_eq!(distinct_count(records("x;y;x", ";"), false), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_code_stats() {
+        let rust = "/* Header\n * spanning lines\n */\n\nfn main() { // entry\n    let x = 1; /* inline */\n    // note\n    /* a */ x\n}\n";
+        assert_eq!(
+            code_stats(rust, &RUST_COMMENTS),
+            CodeStats {
+                code: 4,
+                comment: 4,
+                blank: 1
+            }
+        );
+
+        let python = "# comment\nx = 1  # trailing\n\n/* not a comment */\n";
+        assert_eq!(
+            code_stats(python, &HASH_COMMENTS),
+            CodeStats {
+                code: 2,
+                comment: 1,
+                blank: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_redact() {
+        let text = "Mail jane.doe+work@example.co.uk now";
+        let redacted = redact(text);
+        assert_eq!(redacted, "Mail [EMAIL] now");
+        assert_eq!(count_words(&redacted), 3);
+
+        assert_eq!(
+            redact("Card 4111 1111 1111 1111, call +1 (555) 123-4567 or 06.12.34.56.78."),
+            "Card [CARD], call [PHONE] or [PHONE]."
+        );
+        assert_eq!(redact("Paid 4111111111111111."), "Paid [CARD].");
+        assert_eq!(redact("On 2024-10-14, room 42"), "On 2024-10-14, room 42");
+
+        // lists of numbers are not phone or card numbers
+        let list = "Scores 100 200 300 400, then 1 2 3 4 5 6 7 8 9 10 11 12";
+        assert_eq!(redact(list), list);
+        assert_eq!(redact("٠١٢٣٤٥٦٧٨٩٠"), "٠١٢٣٤٥٦٧٨٩٠");
+    }
+
+    #[test]
+    fn test_strip_markdown_links() {
+        let text = "Read [the guide](http://example.com/a/long/url \"Guide\") first.";
+        let stripped = strip_markdown_links(text);
+        assert_eq!(stripped, "Read the guide first.");
+        assert_eq!(count_words(&stripped), 4);
+
+        let references = "See [docs][1] and ![a logo](img/logo.png).\n\n[1]: https://docs.rs\n";
+        assert_eq!(strip_markdown_links(references), "See docs and a logo.\n\n");
+        assert_eq!(
+            strip_markdown_links("[x] done, a[0] = 1"),
+            "[x] done, a[0] = 1"
+        );
+    }
+
+    #[test]
+    fn test_strip_html() {
+        let text = strip_html("<p>Hello &amp; bye</p>");
+        assert_eq!(text, "Hello & bye");
+        assert_eq!(count_words(&text), 3);
+
+        let page = "<html><head><style>p { color: red; }</style>\
+                    <SCRIPT>var x = 1 < 2;</SCRIPT></head>\
+                    <body><p>One&nbsp;two</p><p>three &lt;4&gt;</p></body></html>";
+        assert_eq!(strip_html(page), "One two\n\nthree <4>");
+        assert_eq!(strip_html("a < b"), "a < b");
+    }
+
+    #[test]
+    fn test_squeeze_repeats() {
+        assert_eq!(squeeze_repeats("soo good", 2), "soo good");
+        assert_eq!(squeeze_repeats("sooo goooood!!!", 2), "soo good!!");
+        assert_eq!(squeeze_repeats("sooo", 1), "so");
+        assert_eq!(squeeze_repeats("a\n\n\n\nb    c", 2), "a\n\n\n\nb    c");
+    }
+
+    #[test]
+    fn test_ascii_quotes() {
+        assert_eq!(
+            ascii_quotes("“It’s ‘fine’”—she said… 1–2"),
+            "\"It's 'fine'\"--she said... 1-2"
+        );
+        assert_eq!(ascii_quotes("plain \"text\""), "plain \"text\"");
+    }
+
+    #[test]
+    fn test_compact_whitespace() {
+        assert_eq!(compact_whitespace("a   b\tc"), "a b c");
+        assert_eq!(compact_whitespace("  a  b \n\nc\t\n"), "a b\n\nc\n");
+        assert_eq!(
+            count_words(&compact_whitespace("a   b\tc")),
+            count_words("a   b\tc")
+        );
+    }
+
+    #[test]
+    fn test_line_width() {
+        assert_eq!(line_width("abc", 4), 3);
+        assert_eq!(line_width("\tabc", 4), 7);
+        assert_eq!(line_width("ab\tc", 4), 5);
+        assert_eq!(line_width("\t", 8), 8);
+    }
+
+    #[test]
+    fn test_code_blocks() {
+        let markdown = "# Demo\n\n```rust\nfn main() {}\n```\n\nText\n\n```rust ignore\nlet a = 1;\nlet b = 2;\n```\n";
+        let fences = code_blocks(markdown);
+        assert_eq!(fences.blocks.len(), 2);
+        assert!(fences.blocks.iter().all(|block| block.language == "rust"));
+        assert_eq!(fences.blocks.iter().map(|b| b.lines).sum::<usize>(), 3);
+        assert_eq!(fences.unclosed, None);
+
+        // a shorter or different fence does not close the block
+        let nested = "````md\n```\ninner\n```\n````\n~~~\nopen\n";
+        let fences = code_blocks(nested);
+        assert_eq!(fences.blocks[0].lines, 3);
+        assert_eq!(fences.blocks[1].language, "");
+        assert_eq!(fences.unclosed, Some(6));
+    }
+
+    #[test]
+    fn test_heading_counts() {
+        let doc = "# Title\n\n## Install\n#hashtag\n ## indented\n```sh\n# comment\n```\n## Usage\n####### seven\n";
+        assert_eq!(heading_counts(doc), [1, 2, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_list_stats() {
+        let doc = "Intro\n\n- one\n  - two\n    * three\n  + four\n1. first\n2) second\n   1. sub\n```\n- code\n```\n---\n-not an item\n";
+        assert_eq!(
+            list_stats(doc),
+            ListStats {
+                bullets: 4,
+                numbered: 3,
+                max_depth: 3
+            }
+        );
+        assert_eq!(list_stats("no lists").max_depth, 0);
+    }
+
+    #[test]
+    fn test_file_ending() {
+        let ending = file_ending("text\n\n\n");
+        assert_eq!(ending.trailing_blank_lines, 2);
+        assert!(ending.ends_with_newline);
+
+        assert_eq!(
+            file_ending("text\n \nmore"),
+            FileEnding {
+                trailing_blank_lines: 0,
+                ends_with_newline: false
+            }
+        );
+        assert_eq!(file_ending("text\n").trailing_blank_lines, 0);
+    }
+
+    #[test]
+    fn test_line_length_buckets() {
+        let text = format!(
+            "{}\n{}\n{}\n\n{}\n",
+            "a".repeat(40),
+            "b".repeat(41),
+            "c".repeat(81),
+            "d".repeat(80)
+        );
+        let counts = line_length_buckets(&text, &[40, 80], 4);
+        assert_eq!(counts, [2, 2, 1]);
+        assert_eq!(counts.iter().sum::<usize>(), count_lines(&text));
+
+        assert_eq!(
+            line_length_buckets("a\nab\n\tx\n", &[3, 1, 3], 4),
+            [1, 1, 1]
+        );
+        assert_eq!(line_length_buckets("ab", &[], 4), [1]);
+    }
+
+    #[test]
+    fn test_long_lines() {
+        let text = "0123456789\n0123456789a\nshort";
+        assert_eq!(long_lines(text, 10, 4), vec![(2, 11)]);
+        assert_eq!(long_lines("\tabcdefg", 10, 4), vec![(1, 11)]);
+        assert!(long_lines(text, 11, 4).is_empty());
+    }
+
+    #[test]
+    fn test_count_emoji() {
+        assert_eq!(count_emoji("hello 😊"), 1);
+        assert_eq!(count_emoji("👨‍👩‍👧"), 1);
+        assert_eq!(count_emoji("🇫🇷 and ⭐"), 2);
+        assert_eq!(count_emoji("no emoji here"), 0);
+    }
+
+    #[test]
+    fn test_distinct_emoji() {
+        assert_eq!(distinct_emoji("😊 👍 😊"), vec!["😊", "👍"]);
+        assert!(distinct_emoji("plain text").is_empty());
+    }
+
+    #[test]
+    fn test_paragraph_word_counts() {
+        let text = "one two three\n\nfour five six seven\neight nine ten\n\neleven twelve";
+        let counts = paragraph_word_counts(text);
+        assert_eq!(counts, [3, 7, 2]);
+        assert_eq!(paragraph_word_stats(&counts), (4.0, 7));
+        assert_eq!(paragraph_word_stats(&[4, 0, 2]), (3.0, 4));
+        assert_eq!(paragraph_word_stats(&[]), (0.0, 0));
+    }
+
+    #[test]
+    fn test_extract_urls() {
+        let text = "See https://example.com/docs, or (http://a.org/x?y=1). Not ftp://b.net";
+        assert_eq!(
+            extract_urls(text),
+            ["https://example.com/docs", "http://a.org/x?y=1"]
+        );
+        assert!(extract_urls("no links here").is_empty());
+    }
+
+    #[test]
+    fn test_unique_urls() {
+        let text = "https://Example.com/docs/ and https://example.com/docs \
+                    https://example.com/docs?page=2 https://example.com/docs/?page=2";
+        assert_eq!(extract_urls(text).len(), 4);
+        assert_eq!(
+            unique_urls(text),
+            [
+                "https://example.com/docs",
+                "https://example.com/docs?page=2"
+            ]
+        );
+        assert_eq!(unique_urls("http://a.org/ http://a.org"), ["http://a.org"]);
+    }
+
+    #[test]
+    fn test_reflow() {
+        assert_eq!(
+            reflow("the quick brown fox\njumps over the lazy dog", 10),
+            "the quick\nbrown fox\njumps over\nthe lazy\ndog\n"
+        );
+        assert_eq!(
+            reflow("a extraordinarily b\n\n\nnext", 5),
+            "a\nextraordinarily\nb\n\nnext\n"
+        );
+        assert_eq!(reflow(" \n", 10), "");
+    }
+
+    #[test]
+    fn test_paragraphs() {
+        let text = "first line\nstill first\n\n  \nsecond\n\n";
+        assert_eq!(paragraphs(text), vec!["first line\nstill first", "second"]);
+        assert!(paragraphs("\n \n").is_empty());
+    }
+
+    #[test]
+    fn test_paragraph_reading_time() {
+        let text = format!("{}\n\n{}", "word ".repeat(100), "word ".repeat(300));
+        let times: Vec<f64> = paragraphs(&text)
+            .iter()
+            .map(|paragraph| reading_time_secs(count_words(paragraph), 200))
+            .collect();
+        assert_eq!(times, vec![30.0, 90.0]);
+        assert_eq!(reading_time_secs(count_words(&text), 200), 120.0);
+        assert_eq!(reading_time_secs(10, 0), 0.0);
+    }
+
+    #[test]
+    fn test_punctuation_stats() {
+        let none = BTreeSet::new();
+        let text = "I came. Did you see me? Wow! Really?! Pi is 3.14 exactly... Yes";
+        let terminators = DEFAULT_SENTENCE_TERMINATORS;
+        assert_eq!(count_questions(text, terminators, &none), 2);
+        assert_eq!(count_exclamations(text, terminators, &none), 1);
+        assert_eq!(count_statements(text, terminators, &none), 2);
+        assert_eq!(count_questions("no punctuation", terminators, &none), 0);
+        assert_eq!(count_sentences(text, terminators, &none), 5);
+    }
+
+    #[test]
+    fn test_punctuation_stats_add_up() {
+        let total = |text: &str, terminators: &str, abbreviations: &BTreeSet<String>| {
+            count_questions(text, terminators, abbreviations)
+                + count_exclamations(text, terminators, abbreviations)
+                + count_statements(text, terminators, abbreviations)
+        };
+        let xq = BTreeSet::from(["xq".to_string()]);
+        let text = "Xq. went home.";
+        assert_eq!(count_sentences(text, DEFAULT_SENTENCE_TERMINATORS, &xq), 1);
+        assert_eq!(total(text, DEFAULT_SENTENCE_TERMINATORS, &xq), 1);
+
+        let none = BTreeSet::new();
+        let text = "one; two; three?";
+        assert_eq!(count_sentences(text, ";", &none), 2);
+        assert_eq!(count_questions(text, ";", &none), 0);
+        assert_eq!(count_statements(text, ";", &none), 2);
+    }
+
+    #[test]
+    fn test_sentences() {
+        let none = BTreeSet::new();
+        assert_eq!(
+            sentences(
+                "Hi there. Pi is 3.14! Ok? trailing",
+                DEFAULT_SENTENCE_TERMINATORS,
+                &none
+            ),
+            ["Hi there.", "Pi is 3.14!", "Ok?"]
+        );
+    }
+
+    #[test]
+    fn test_sentences_before_closing_quotes() {
+        let none = BTreeSet::new();
+        let terminators = DEFAULT_SENTENCE_TERMINATORS;
+        assert_eq!(
+            sentences(
+                r#""Hi." She left. (Really?) “Yes!” Done"#,
+                terminators,
+                &none
+            ),
+            [r#""Hi.""#, "She left.", "(Really?)", "“Yes!”"]
+        );
+        assert_eq!(count_sentences(r#"He said "no."x"#, terminators, &none), 0);
+        assert_eq!(count_statements(r#""Hi." "Bye.""#, terminators, &none), 2);
+        assert_eq!(count_questions("(Why?) ", terminators, &none), 1);
+    }
+
+    #[test]
+    fn test_sentence_ending_breakdown() {
+        let none = BTreeSet::new();
+        let terminators = ".!?…";
+        let text = "It rains. Does it? It pours! Well... Who knew?! Then… silence. and more";
+        assert_eq!(
+            sentence_ending_breakdown(text, terminators, &none),
+            SentenceEndings {
+                period: 2,
+                question: 2,
+                exclamation: 1,
+                ellipsis: 2,
+                other: 0,
+                unterminated: 1,
+            }
+        );
+        let done = sentence_ending_breakdown("Done.", DEFAULT_SENTENCE_TERMINATORS, &none);
+        assert_eq!(done.unterminated, 0);
+        assert_eq!(
+            sentence_ending_breakdown("", DEFAULT_SENTENCE_TERMINATORS, &none),
+            SentenceEndings::default()
+        );
+    }
+
+    #[test]
+    fn test_sentence_endings_add_up() {
+        let extra = BTreeSet::from(["capt".to_string()]);
+        for (text, terminators) in [
+            ("Capt. Hook. Dr. Who? Yes! 今日は晴れ。 Ok; fine", ".!?。;"),
+            ("One; two. Three? four", ";?"),
+            ("Mr. Smith... left!? no", DEFAULT_SENTENCE_TERMINATORS),
+        ] {
+            let endings = sentence_ending_breakdown(text, terminators, &extra);
+            let total = endings.period
+                + endings.question
+                + endings.exclamation
+                + endings.ellipsis
+                + endings.other;
+            assert_eq!(total, count_sentences(text, terminators, &extra));
+            assert_eq!(endings.unterminated, 1);
+        }
+        let custom = sentence_ending_breakdown("A; b。 c.", ";。", &extra);
+        assert_eq!((custom.other, custom.period), (2, 0));
+    }
+
+    #[test]
+    fn test_words_per_sentence_buckets() {
+        let none = BTreeSet::new();
+        let text = "One two. Three four five six seven eight! Nine? Ten eleven twelve.";
+        let counts = words_per_sentence(text, DEFAULT_SENTENCE_TERMINATORS, &none);
+        assert_eq!(counts, [2, 6, 1, 3]);
+
+        let buckets = bucket_counts(&counts, 5);
+        assert_eq!(buckets, [(1, 3), (6, 1)]);
+        let total: usize = buckets.iter().map(|&(_, count)| count).sum();
+        assert_eq!(total, counts.len());
+
+        assert_eq!(bucket_counts(&[12], 5), [(1, 0), (6, 0), (11, 1)]);
+        assert!(bucket_counts(&[], 5).is_empty());
+    }
+
+    #[test]
+    fn test_passive_sentences() {
+        let none = BTreeSet::new();
+        let terminators = DEFAULT_SENTENCE_TERMINATORS;
+        assert_eq!(
+            passive_sentences("The ball was thrown.", terminators, &none),
+            ["The ball was thrown."]
+        );
+        assert!(passive_sentences("He threw the ball.", terminators, &none).is_empty());
+        assert_eq!(
+            passive_sentences(
+                "We wrote it. It is quickly parsed! Mistakes were made?",
+                terminators,
+                &none
+            ),
+            ["It is quickly parsed!", "Mistakes were made?"]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_sentences() {
+        let none = BTreeSet::new();
+        let text = "Buy now. Prices are low!  buy   NOW. Call us.";
+        assert_eq!(
+            count_sentences(text, DEFAULT_SENTENCE_TERMINATORS, &none),
+            4
+        );
+        assert_eq!(
+            unique_sentence_count(text, DEFAULT_SENTENCE_TERMINATORS, &none),
+            3
+        );
+        assert_eq!(
+            duplicate_sentences(text, DEFAULT_SENTENCE_TERMINATORS, &none),
+            [("Buy now.", 2)]
+        );
+        assert!(duplicate_sentences("One. Two.", DEFAULT_SENTENCE_TERMINATORS, &none).is_empty());
+    }
+
+    #[test]
+    fn test_sentence_fragments() {
+        let none = BTreeSet::new();
+        let text = "Yes. I went home today. Not really!";
+        let terminators = DEFAULT_SENTENCE_TERMINATORS;
+        assert_eq!(
+            sentence_fragments(text, DEFAULT_FRAGMENT_WORDS, terminators, &none),
+            ["Yes.", "Not really!"]
+        );
+        assert_eq!(sentence_fragments(text, 2, terminators, &none), ["Yes."]);
+    }
+
+    #[test]
+    fn test_lowercase_sentences() {
+        let none = BTreeSet::new();
+        let terminators = DEFAULT_SENTENCE_TERMINATORS;
+        assert_eq!(
+            lowercase_sentences("Hello. goodbye.", terminators, &none),
+            [2]
+        );
+        assert_eq!(
+            lowercase_sentences(
+                r#""no," he said. (yes) fine. 42 is. Ok."#,
+                terminators,
+                &none
+            ),
+            [1, 2]
+        );
+        assert_eq!(
+            capitalized_sentence_count(
+                r#""no," he said. (yes) fine. 42 is. Ok."#,
+                terminators,
+                &none
+            ),
+            1
+        );
+    }
+
+    #[test]
+    fn test_long_sentences() {
+        let none = BTreeSet::new();
+        let text = "One two three. One two three four five six seven! \
+                    One two three four five? Six seven eight.";
+        let longest = long_sentences(text, 2, DEFAULT_SENTENCE_TERMINATORS, &none);
+        assert_eq!(
+            longest,
+            [
+                ("One two three four five six seven!", 7),
+                ("One two three four five?", 5)
+            ]
+        );
+        // ties keep their order of appearance
+        let tied = long_sentences(text, 4, DEFAULT_SENTENCE_TERMINATORS, &none);
+        assert_eq!(tied[2], ("One two three.", 3));
+        assert_eq!(tied[3], ("Six seven eight.", 3));
+    }
+
+    #[test]
+    fn test_count_sentences_custom_terminators() {
+        let none = BTreeSet::new();
+        let text = "今日は晴れです。明日は雨です。そうですか";
+        assert_eq!(count_sentences(text, "。", &none), 2);
+        assert_eq!(
+            count_sentences(text, DEFAULT_SENTENCE_TERMINATORS, &none),
+            0
+        );
+        assert_eq!(count_sentences("यह है। वह है।", "।", &none), 2);
+        // only the listed characters are boundaries
+        assert_eq!(count_sentences("One. Two! Three?", "!", &none), 1);
+    }
+
+    #[test]
+    fn test_sentences_after_abbreviations() {
+        let none = BTreeSet::new();
+        let terminators = DEFAULT_SENTENCE_TERMINATORS;
+        assert_eq!(
+            count_sentences("Dr. Smith went home.", terminators, &none),
+            1
+        );
+        assert_eq!(
+            count_sentences("See (e.g. here) Mr. Lee. Ok.", terminators, &none),
+            2
+        );
+        assert_eq!(count_sentences("He met the Dr.", terminators, &none), 1);
+        assert_eq!(count_sentences("He met the Dr.\n", terminators, &none), 1);
+
+        let text = "Ask Capt. Hook. He knows.";
+        assert_eq!(count_sentences(text, terminators, &none), 3);
+        let extra = BTreeSet::from(["capt".to_string()]);
+        assert_eq!(count_sentences(text, terminators, &extra), 2);
+    }
+
+    #[test]
+    fn test_script_breakdown() {
+        let counts = script_breakdown("Hello мир, 42!");
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts["Latin"], 5);
+        assert_eq!(counts["Cyrillic"], 3);
+
+        assert_eq!(script_breakdown("日本 é")["CJK"], 2);
+        assert!(script_breakdown("123 ...").is_empty());
+    }
+
+    #[test]
+    fn test_line_initial_frequencies() {
+        let markdown = "# Title\n\n- one\n- two\n  - nested\n1. first\nText\n";
+        let frequencies = line_initial_frequencies(markdown);
+        assert_eq!(frequencies[&'-'], 3);
+        assert_eq!(frequencies[&'#'], 1);
+        assert_eq!(frequencies[&'1'], 1);
+        assert_eq!(frequencies[&'t'], 1);
+        assert_eq!(frequencies.values().sum::<usize>(), 6);
+    }
+
+    #[test]
+    fn test_sum_numbers() {
+        assert_eq!(sum_numbers("prices 10 20 30"), 60.0);
+        assert_eq!(sum_numbers("no numbers here"), 0.0);
+
+        let found = numbers("Paid $1,200.50 and -0.5, then 3%. Version 1.2.3 or 12,34?");
+        assert_eq!(found.values, [1200.5, -0.5, 3.0]);
+        assert_eq!(found.malformed, ["1.2.3", "12,34?"]);
+    }
+
+    #[test]
+    fn test_detect_language() {
+        let english = "The quick brown fox jumps over the lazy dog while the farmer \
+                       watches from the porch and wonders where the summer went.";
+        let (code, confidence) = detect_language(english).unwrap_or(("none", 0.0));
+        assert_eq!(code, "eng");
+        assert!(confidence > 0.5);
+        assert_eq!(detect_language("Hi there"), None);
+    }
+
+    #[test]
+    fn test_mixed_script_words() {
+        // "ра" is Cyrillic
+        let text = "Log in to раypal, not paypal. раypal again! 日本語です";
+        assert_eq!(mixed_script_words(text), ["раypal"]);
+        assert!(mixed_script_words("мир hello").is_empty());
+    }
+
+    #[test]
+    fn test_initial_letter_frequencies() {
+        let frequencies = initial_letter_frequencies("apple avocado banana");
+        assert_eq!(frequencies, BTreeMap::from([('a', 2), ('b', 1)]));
+        assert_eq!(most_common_initial(&frequencies), Some('a'));
+
+        let frequencies = initial_letter_frequencies("\"Zebra\" 42 zoo Boat");
+        assert_eq!(frequencies, BTreeMap::from([('b', 1), ('z', 2)]));
+        assert_eq!(most_common_initial(&BTreeMap::new()), None);
+    }
+
+    #[test]
+    fn test_normalize_word() {
+        assert_eq!(normalize_word("(Hello!)", false), Some("Hello".to_string()));
+        assert_eq!(normalize_word("Hello", true), Some("hello".to_string()));
+        assert_eq!(normalize_word("--", false), None);
+    }
+
+    #[test]
+    fn test_word_frequencies() {
+        let frequencies = word_frequencies("The cat, the CAT.", true);
+        assert_eq!(
+            frequencies,
+            BTreeMap::from([("cat".to_string(), 2), ("the".to_string(), 2)])
+        );
+        assert_eq!(word_frequencies("Cat cat", false).len(), 2);
+    }
+
+    #[test]
+    fn test_ngram_frequencies() {
+        let frequencies = ngram_frequencies("a b c a b", 2, false);
+        assert_eq!(frequencies["a b"], 2);
+        assert_eq!(frequencies.len(), 3);
+        assert!(ngram_frequencies("a b", 3, false).is_empty());
+        assert!(ngram_frequencies("a b", 0, false).is_empty());
+    }
+
+    #[test]
+    fn test_repeated_ngrams() {
+        let text = "As stated above, the results vary. Clearly, as stated above the data agrees.";
+        assert_eq!(
+            repeated_ngrams(text, 3, true),
+            [
+                ("as stated above".to_string(), 2),
+                ("stated above the".to_string(), 2)
+            ]
+        );
+        assert!(repeated_ngrams("no phrase repeats here", 3, true).is_empty());
+    }
+
+    #[test]
+    fn test_words_starting_with() {
+        let words = words_starting_with("apple apricot banana", "ap");
+        assert_eq!(words.values().sum::<usize>(), 2);
+        assert_eq!(words.keys().collect::<Vec<_>>(), ["apple", "apricot"]);
+        assert_eq!(words_starting_with("Apple (apple) APEX", "AP")["apple"], 2);
+        assert_eq!(words_starting_with("a b b", "").values().sum::<usize>(), 3);
+    }
+
+    #[test]
+    fn test_hapax_legomena() {
+        assert_eq!(hapax_legomena("a b b c", false), ["a", "c"]);
+        assert_eq!(hapax_legomena("The the", false), ["The", "the"]);
+        assert!(hapax_legomena("The the", true).is_empty());
+        assert_eq!(hapax_legomena("The cat, the dog.", true), ["cat", "dog"]);
+        assert!(hapax_legomena("", true).is_empty());
+    }
+
+    #[test]
+    fn test_lexical_diversity() {
+        assert_eq!(lexical_diversity("a b c", false).type_token_ratio, 1.0);
+        let repeated = lexical_diversity("a a a", false);
+        assert!((repeated.type_token_ratio - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(repeated.hapax_ratio, 0.0);
+
+        let diversity = lexical_diversity("A a b c", true);
+        assert_eq!(diversity.type_token_ratio, 0.75);
+        assert_eq!(diversity.hapax_ratio, 0.5);
+        assert_eq!(diversity.root_ttr, 1.5);
+        assert_eq!(lexical_diversity("", true), Diversity::default());
+    }
+
+    #[test]
+    fn test_title_case_lines() {
+        let text = "The Quick Brown Fox\nthe quick fox\nWar and Peace\n\n1984\nThe End, Part 2";
+        assert_eq!(title_case_lines(text), [1, 3, 6]);
+        assert_eq!(title_case_lines("And Then"), [1]);
+        assert!(title_case_lines("the End").is_empty());
+    }
+
+    #[test]
+    fn test_lines_with_repeated_words() {
+        let text = "go go go here\nfine line\nGo gO go again";
+        assert_eq!(lines_with_repeated_words(text, 2), [1, 3]);
+        assert!(lines_with_repeated_words(text, 3).is_empty());
+        assert_eq!(lines_with_repeated_words("a b a", 1), [1]);
+    }
+
+    #[test]
+    fn test_count_contractions() {
+        assert_eq!(count_contractions("don't"), 1);
+        assert_eq!(count_contractions("dogs"), 0);
+        assert_eq!(count_contractions("The dog's bone"), 0);
+        assert_eq!(
+            count_contractions("It's late, you'll see. I'm sure they\u{2019}re gone, isn't it?"),
+            5
+        );
+        assert_eq!(count_contractions("'quoted' words"), 0);
+    }
+
+    #[test]
+    fn test_words_in_quotes() {
+        assert_eq!(words_in_quotes(r#"He said "hello there" loudly"#), 2);
+        assert_eq!(
+            words_in_quotes("\u{201C}Run,\u{201D} she said. \u{201C}Now, please.\u{201D}"),
+            3
+        );
+        // the dangling quote opens nothing that closes
+        assert_eq!(words_in_quotes(r#""one two" and "three four"#), 2);
+        assert_eq!(words_in_quotes("no quotes"), 0);
+    }
+
+    #[test]
+    fn test_caps_ratio() {
+        assert!((caps_ratio("THIS is BAD") - 200.0 / 3.0).abs() < 1e-9);
+        assert_eq!(caps_ratio("I SAID NO, A-OK?"), 100.0);
+        assert_eq!(caps_ratio("I A 42"), 0.0);
+        assert_eq!(caps_ratio(""), 0.0);
+    }
+
+    #[test]
+    fn test_count_acronyms() {
+        assert_eq!(
+            count_acronyms("NASA and the FBI"),
+            [("FBI".to_string(), 1), ("NASA".to_string(), 1)]
+        );
+        assert_eq!(
+            count_acronyms("I saw A UFO. The UFO (an MP3 of it) had 3D sound, OK?"),
+            [
+                ("UFO".to_string(), 2),
+                ("MP3".to_string(), 1),
+                ("OK".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encoded_sizes() {
+        // é is 2 bytes in UTF-8, 日 is 3 and 😀 is 4; UTF-16 takes 2, 2 and 4
+        let sizes = encoded_sizes("aé日😀");
+        assert_eq!(
+            sizes,
+            EncodedSizes {
+                utf8: 10,
+                utf16: 10,
+                latin1: 2,
+                latin1_unrepresentable: 2,
+            }
+        );
+        let ascii = encoded_sizes("plain");
+        assert_ne!(ascii.utf16, ascii.utf8);
+        assert_eq!((ascii.utf16, ascii.latin1), (10, 5));
+        assert_ne!(encoded_sizes("日本語").utf16, encoded_sizes("日本語").utf8);
+    }
+
+    #[test]
+    fn test_digit_ratio() {
+        assert_eq!(digit_ratio("abc123"), 50.0);
+        assert_eq!(digit_ratio("٣ é"), 0.0);
+        assert_eq!(digit_ratio(""), 0.0);
+    }
+
+    #[test]
+    fn test_stopword_ratio() {
+        assert_eq!(stopword_ratio("The cat and the dog."), 60.0);
+        assert_eq!(stopword_ratio("lorem ipsum"), 0.0);
+        assert_eq!(stopword_ratio(""), 0.0);
+    }
+
+    #[test]
+    fn test_overused_words() {
+        assert_eq!(
+            overused_words("cat cat cat dog", 2, false),
+            vec![("cat".to_string(), 3)]
+        );
+        assert_eq!(
+            overused_words("the the the dog dog bird", 1, false),
+            vec![("dog".to_string(), 2)]
+        );
+        assert!(overused_words("cat cat", 2, false).is_empty());
+    }
+
+    #[test]
+    fn test_diff_words() {
+        let (added, removed) = diff_words("a b", "b c", false);
+        assert_eq!(added, vec!["c"]);
+        assert_eq!(removed, vec!["a"]);
+
+        let (added, removed) = diff_words("Apple pie", "apple tart", true);
+        assert_eq!(added, vec!["tart"]);
+        assert_eq!(removed, vec!["pie"]);
+        assert_eq!(diff_words("Apple", "apple", false).0, vec!["apple"]);
+    }
+
+    #[test]
+    fn test_analyze() -> FunctionResult<()> {
+        let text: &str = "aaa\naa";
+        let expected_report: Report = Report {
+            char_count: 6,
+            chars_no_spaces: 5,
+            word_count: 2,
+            line_count: 2,
+            unique_line_count: 2,
+            avg_words_per_line: 1.0,
+        };
+
+        assert_eq!(analyze(text)?, expected_report);
+        Ok(())
+    }
+
+    #[test]
+    fn test_report_add_assign() -> FunctionResult<()> {
+        let mut total = Report::default();
+        total += &analyze("lorem ipsum\n")?;
+        total += &analyze("dolor")?;
+
+        assert_eq!(total, analyze("lorem ipsum\ndolor")?);
+        assert_eq!(total.avg_words_per_line, 1.5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_average() {
+        assert_eq!(average(3, 2), 1.5);
+        assert_eq!(average(3, 0), 0.0);
+    }
+
+    #[test]
+    fn test_accumulator_chunks() -> FunctionResult<()> {
+        let text = "lorem ip\nsum\n\ndolor😊\r\nsum\nsum";
+        let mut accumulator = Accumulator::new();
+        for chunk in ["lorem i", "p\nsu", "m\n", "\ndolor😊\r", "\nsum\ns", "um"] {
+            accumulator.feed(chunk);
+        }
+
+        assert_eq!(accumulator.report(), analyze(text)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_accumulator_profiled() {
+        let text = "lorem ipsum\ndolor";
+        let mut plain = Accumulator::new();
+        let mut profiled = Accumulator::profiled();
+        plain.feed(text);
+        profiled.feed(text);
+
+        assert_eq!(plain.report(), profiled.report());
+        assert!(plain.profile().is_none());
+        assert!(profiled.profile().is_some());
+    }
+
+    // Hands out one byte per read to split multi-byte characters
+    struct ByteReader(Cursor<Vec<u8>>);
+
+    impl Read for ByteReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(1);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    #[test]
+    fn test_analyze_reader() -> FunctionResult<()> {
+        let text = "lorem ipsum\n  dolor sit\namet 😊 é\n";
+
+        let report = analyze_reader(Cursor::new(text.as_bytes().to_vec()))?;
+        assert_eq!(report, analyze(text)?);
+
+        let report = analyze_reader(ByteReader(Cursor::new(text.as_bytes().to_vec())))?;
+        assert_eq!(report, analyze(text)?);
+
+        assert!(analyze_reader(Cursor::new(vec![0xff, 0xfe])).is_err());
+        Ok(())
+    }
+}
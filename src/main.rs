@@ -10,19 +10,66 @@
 
 // ********* Uses **********
 
+mod blame;
+mod cache;
+#[cfg(feature = "http")]
+mod fetch;
+mod output;
+mod walk;
+mod watch;
+
 use atty::Stream;
-use clap::{Parser, Subcommand};
-use prettytable::{Table, cell, row};
+use blame::{author_lines, author_reports};
+use cache::{Cache, DEFAULT_CACHE_FILE, cache_key};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use output::{
+    DEFAULT_PRECISION, FileReport, Format, KeyMap, banner, checksum_line, columns_lines,
+    columns_to_csv, columns_to_json, format_float, json_fields, sidecar_line, sidecar_path, to_csv,
+    to_json, with_checksum_csv, with_checksum_json, with_metadata_csv, with_metadata_json,
+    write_frequency_csv,
+};
+use rayon::prelude::*;
+use regex::Regex;
+use serde_json::Value;
 use std::{
-    fs,
-    io::{self, Read},
-    path::PathBuf,
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
 };
+use text_analyzer::{
+    Accumulator, CommentSyntax, DASHES, DEFAULT_SENTENCE_TERMINATORS, DEFAULT_SQUEEZE_THRESHOLD,
+    FunctionResult, HASH_COMMENTS, HYPHENS, Profile, RUST_COMMENTS, Report, analyze, ascii_quotes,
+    average, bucket_counts, capitalized_sentence_count, caps_ratio, code_blocks, code_stats,
+    compact_whitespace, count_acronyms, count_contractions, count_emoji, count_exclamations,
+    count_lines, count_questions, count_records, count_sentences, count_statements, count_words,
+    detect_language, diff_words, digit_ratio, distinct_count, distinct_emoji, duplicate_sentences,
+    encoded_sizes, extract_urls, file_ending, hapax_legomena, heading_counts,
+    initial_letter_frequencies, lexical_diversity, line_initial_frequencies, line_length_buckets,
+    line_width, lines_with_repeated_words, list_stats, long_lines, long_sentences,
+    lowercase_sentences, metrics, metrics::Metric, metrics::MetricValue, mixed_script_words,
+    most_common_initial, numbers, overused_words, paragraph_word_counts, paragraph_word_stats,
+    paragraphs, passive_sentences, reading_time_secs, records, redact, reflow, repeated_ngrams,
+    script_breakdown, sentence_ending_breakdown, sentence_fragments, sentences, sketch::TopWords,
+    split_words_at, squeeze_repeats, stopword_ratio, strip_html, strip_markdown_links,
+    title_case_lines, tokens, unique_sentence_count, unique_urls, vocabulary, whitespace_breakdown,
+    word_frequencies, words_in_quotes, words_per_sentence, words_starting_with,
+};
+use unicode_normalization::UnicodeNormalization;
+use walk::{PathFilter, expand_inputs};
+use watch::{DEFAULT_REFRESH_MS, RedrawTimer, modified_times};
 
 // ********* ratatui Stuff **********
 use crossterm::{
+    event::{self, Event, KeyCode},
     execute,
-    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+    terminal::{
+        self, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
+    },
 };
 use ratatui::{Terminal, backend::CrosstermBackend};
 
@@ -31,7 +78,7 @@ fn init_terminal() -> io::Result<Terminal<CrosstermBackend<std::io::Stdout>>> {
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen)?;
     let backend = CrosstermBackend::new(stdout);
-    Ok(Terminal::new(backend)?)
+    Terminal::new(backend)
 }
 
 fn restore_terminal() -> io::Result<()> {
@@ -42,30 +89,156 @@ fn restore_terminal() -> io::Result<()> {
 }
 
 use ratatui::layout::{Constraint, Direction, Layout};
-use ratatui::style::{Color, Style};
-use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, Tabs, Wrap};
 
-fn ui(f: &mut ratatui::Frame, report: &Report) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .margin(2)
-        .constraints([Constraint::Percentage(100)].as_ref())
-        .split(f.size());
+// Below this width the report switches to the compact layout
+const COMPACT_WIDTH: u16 = 40;
 
-    let text = format!(
-        "Characters: {}\nWords: {}\nLines: {}",
-        report.char_count, report.word_count, report.line_count
-    );
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TuiLayout {
+    // margins around the block, lines never wrapped
+    Regular,
+    // no margins, long lines wrapped so every metric stays visible
+    Compact,
+}
+
+impl TuiLayout {
+    fn margin(self) -> u16 {
+        match self {
+            TuiLayout::Regular => 2,
+            TuiLayout::Compact => 0,
+        }
+    }
+}
+
+// Regular only if the terminal is wide enough and the longest line fits
+// inside the margins and borders
+fn choose_layout(width: u16, longest_line: usize) -> TuiLayout {
+    let inner = usize::from(width).saturating_sub(2 * usize::from(TuiLayout::Regular.margin()) + 2);
+    if width < COMPACT_WIDTH || longest_line > inner {
+        TuiLayout::Compact
+    } else {
+        TuiLayout::Regular
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum ThemeName {
+    Dark,
+    Light,
+    // no color at all, the selected tab is shown reversed
+    Mono,
+}
+
+// Styles of the report TUI: `text` for the foreground and background, `accent` for the selected tab
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Theme {
+    text: Style,
+    accent: Style,
+}
+
+impl Theme {
+    fn new(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Dark => Theme {
+                text: Style::default().fg(Color::White),
+                accent: Style::default().fg(Color::Yellow),
+            },
+            ThemeName::Light => Theme {
+                text: Style::default().fg(Color::Black).bg(Color::White),
+                accent: Style::default().fg(Color::Blue),
+            },
+            ThemeName::Mono => Theme {
+                text: Style::default(),
+                accent: Style::default().add_modifier(Modifier::REVERSED),
+            },
+        }
+    }
+}
 
-    let paragraph = Paragraph::new(text)
+fn report_paragraph(lines: &[String], layout: TuiLayout, theme: Theme) -> Paragraph<'static> {
+    let mut paragraph = Paragraph::new(lines.join("\n"))
         .block(
             Block::default()
                 .title("Text Analysis Report")
                 .borders(Borders::ALL),
         )
-        .style(Style::default().fg(Color::White));
+        .style(theme.text);
+    if layout == TuiLayout::Compact {
+        paragraph = paragraph.wrap(Wrap { trim: false });
+    }
+    paragraph
+}
+
+fn longest_line(lines: &[String]) -> usize {
+    lines
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0)
+}
+
+fn ui(f: &mut ratatui::Frame, lines: &[String], theme: Theme) {
+    let layout = choose_layout(f.size().width, longest_line(lines));
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(layout.margin())
+        .constraints([Constraint::Percentage(100)].as_ref())
+        .split(f.size());
+
+    f.render_widget(report_paragraph(lines, layout, theme), chunks[0]);
+}
+
+// One page of the report TUI
+struct TuiView {
+    title: &'static str,
+    lines: Vec<String>,
+}
+
+const VIEW_TITLES: [&str; 4] = ["Summary", "Frequencies", "Line Stats", "Readability"];
+
+// Tab and Shift-Tab wrap around at both ends
+fn next_view(index: usize, count: usize) -> usize {
+    if count == 0 { 0 } else { (index + 1) % count }
+}
+
+fn previous_view(index: usize, count: usize) -> usize {
+    if count == 0 {
+        0
+    } else {
+        (index + count - 1) % count
+    }
+}
+
+// The tab bar on top, only when there is more than one view
+fn tabbed_ui(f: &mut ratatui::Frame, views: &[TuiView], selected: usize, theme: Theme) {
+    let Some(view) = views.get(selected) else {
+        return;
+    };
+    let layout = choose_layout(f.size().width, longest_line(&view.lines));
+    let tab_height = if views.len() > 1 { 3 } else { 0 };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(layout.margin())
+        .constraints([Constraint::Length(tab_height), Constraint::Min(0)].as_ref())
+        .split(f.size());
 
-    f.render_widget(paragraph, chunks[0]);
+    if views.len() > 1 {
+        let titles: Vec<Line> = views
+            .iter()
+            .enumerate()
+            .map(|(i, view)| Line::from(format!("{} {}", i + 1, view.title)))
+            .collect();
+        let tabs = Tabs::new(titles)
+            .block(Block::default().borders(Borders::ALL))
+            .style(theme.text)
+            .select(selected)
+            .highlight_style(theme.accent);
+        f.render_widget(tabs, chunks[0]);
+    }
+    f.render_widget(report_paragraph(&view.lines, layout, theme), chunks[1]);
 }
 
 // ********* CLI Stuff **********
@@ -82,32 +255,844 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     // Analyze a text
-    Analyze {
-        // Specifiy a file
-        #[arg(
-            short,
-            long,
-            value_name = "FILE",
-            help = "Text file to analyze (default: stdin)"
-        )]
-        file: Option<PathBuf>,
-    },
+    Analyze(Box<AnalyzeArgs>),
+
+    // Compare two texts
+    Compare(CompareArgs),
+
+    // List the metrics available to --columns
+    Metrics,
+
+    // Sum JSON reports written by analyze --format json
+    Merge(MergeArgs),
+}
+
+#[derive(Args)]
+struct AnalyzeArgs {
+    // Specifiy one or more files
+    #[arg(
+        short,
+        long = "file",
+        value_name = "FILE",
+        num_args = 1..,
+        help = "Text file(s) to analyze, directories are walked recursively (default: stdin)"
+    )]
+    files: Vec<PathBuf>,
+
+    #[cfg(feature = "http")]
+    #[arg(
+        long,
+        value_name = "URL",
+        conflicts_with = "files",
+        help = "Fetch and analyze URL instead of a file"
+    )]
+    url: Option<String>,
+
+    // Filters for files found in directories
+    #[arg(
+        long,
+        value_name = "GLOB",
+        help = "Only analyze files in directories matching GLOB (repeatable)"
+    )]
+    include: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "GLOB",
+        help = "Skip files in directories matching GLOB, even if included (repeatable)"
+    )]
+    exclude: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Descend at most N levels below a directory, 0 keeps only its direct files"
+    )]
+    max_depth: Option<usize>,
+
+    #[arg(
+        long,
+        help = "List the files that would be analyzed and exit without analyzing them"
+    )]
+    dry_run: bool,
+
+    // Live view
+    #[arg(long, help = "Keep the TUI open and redraw it when the files change")]
+    watch: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["watch", "record_sep", "split_hyphens", "split_dashes"],
+        help = "Stream the input, a single file or stdin, and print one JSON object of the running counts per line"
+    )]
+    per_line_json: bool,
+
+    #[arg(
+        long,
+        requires = "files",
+        conflicts_with_all = [
+            "watch", "per_line_json", "concat", "sort_files", "group_by", "skip_empty",
+            "dump_tokens", "vocabulary", "annotate", "cache", "profile",
+        ],
+        help = "Analyze the files on all cores; plain output prints each one's section in path order as soon as it is ready"
+    )]
+    parallel: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = DEFAULT_REFRESH_MS,
+        help = "Minimum milliseconds between two redraws in watch mode"
+    )]
+    refresh_ms: u64,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value = "dark",
+        help = "Colors of the TUI, mono uses none"
+    )]
+    theme: ThemeName,
+
+    // Empty inputs
+    #[arg(long, help = "Warn on stderr about empty files")]
+    warn_empty: bool,
+
+    #[arg(long, help = "Leave empty files out of the output and totals")]
+    skip_empty: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHAT",
+        num_args = 0..=1,
+        default_missing_value = "words",
+        help = "Exit with status 1 if an input has no words (default) or no bytes"
+    )]
+    fail_on_empty: Option<EmptyCheck>,
+
+    // One combined document
+    #[arg(
+        long,
+        help = "Analyze piped stdin followed by all files, joined as is, as a single document"
+    )]
+    concat: bool,
+
+    // Ordering of multi-file output
+    #[arg(
+        long,
+        value_name = "KEY",
+        help = "Order per-file output by name or by a metric (default: command-line order)"
+    )]
+    sort_files: Option<SortKey>,
+
+    #[arg(long, requires = "sort_files", help = "Sort files in descending order")]
+    reverse: bool,
+
+    // Subtotals for multi-file mode
+    #[arg(
+        long,
+        value_name = "KEY",
+        help = "Group multi-file reports by extension or directory"
+    )]
+    group_by: Option<GroupBy>,
+
+    // Give up on stdin after a delay
+    #[arg(
+        long,
+        value_name = "SECS",
+        help = "Abort if stdin does not reach EOF within SECS seconds (default: wait forever)"
+    )]
+    stdin_timeout: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "MS",
+        conflicts_with = "stdin_timeout",
+        help = "Stop reading stdin after MS milliseconds and report the partial counts"
+    )]
+    time_budget: Option<u64>,
+
+    // Guard against huge files
+    #[arg(
+        long,
+        value_name = "BYTES",
+        value_parser = parse_size,
+        help = "Refuse files larger than BYTES, suffixes K, M and G allowed (e.g. 10M)"
+    )]
+    max_file_size: Option<u64>,
+
+    #[arg(
+        long,
+        requires = "max_file_size",
+        help = "Analyze files over --max-file-size anyway"
+    )]
+    force: bool,
+
+    // Custom line delimiter
+    #[arg(
+        long,
+        value_name = "STR",
+        value_parser = parse_separator,
+        help = "Record separator used instead of newlines for line counting (supports \\0, \\t, \\n)"
+    )]
+    record_sep: Option<String>,
+
+    #[arg(
+        long,
+        help = "Ignore leading and trailing whitespace when counting unique lines"
+    )]
+    trim_lines: bool,
+
+    // Output
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Format::Tui,
+        help = "Output format"
+    )]
+    format: Format,
+
+    #[arg(
+        long,
+        value_name = "MAP",
+        value_parser = parse_key_map,
+        help = "Rename JSON keys, e.g. \"char_count=chars,word_count=words\""
+    )]
+    json_keys: Option<KeyMap>,
+
+    #[arg(
+        long,
+        value_name = "JSON_FILE",
+        help = "Print the change of each metric against a report saved with --format json"
+    )]
+    baseline: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Print how each metric deviates from the average of the files in DIR"
+    )]
+    compare_to_dir: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Also write the report to PATH, in the chosen format (plain text for the TUI)"
+    )]
+    tee: Option<PathBuf>,
+
+    #[arg(
+        long,
+        requires = "tee",
+        help = "Append to the --tee file instead of overwriting it; a csv header is only written once"
+    )]
+    append: bool,
+
+    #[arg(
+        long,
+        help = "End the report with its SHA-256, and write PATH.sha256 next to the --tee file"
+    )]
+    checksum: bool,
+
+    #[arg(
+        long,
+        help = "Add the generator name and version to json and csv output"
+    )]
+    with_metadata: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = DEFAULT_PRECISION,
+        help = "Decimal places of averages and other fractional metrics, in every format"
+    )]
+    precision: usize,
+
+    #[arg(
+        long,
+        help = "Print the file name in big letters above plain and TUI reports"
+    )]
+    banner: bool,
+
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        help = "Print TEMPLATE once per file instead of the report, e.g. \"{words} words, {lines} lines\""
+    )]
+    template: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "METRICS",
+        value_delimiter = ',',
+        help = "Only output these metrics, comma separated, e.g. words,lines"
+    )]
+    columns: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "METRIC",
+        help = "Exit with the value of METRIC (e.g. words), rounded down and clamped to 0-255; \
+                counts are summed over the files, averages averaged, lint failures still exit with 1"
+    )]
+    exit_with: Option<String>,
+
+    // Tokenizer debugging
+    #[arg(
+        long,
+        help = "Print each word as tokenized, one per line, instead of the report"
+    )]
+    dump_tokens: bool,
+
+    // Word lists
+    #[arg(
+        long,
+        help = "Print the sorted unique words, one per line, instead of the report"
+    )]
+    vocabulary: bool,
+
+    #[arg(
+        long,
+        requires = "vocabulary",
+        help = "With --vocabulary, order words by decreasing frequency"
+    )]
+    sort_by_frequency: bool,
+
+    // Review view
+    #[arg(
+        long,
+        help = "Print the text with line numbers before the report, which is then printed as plain text"
+    )]
+    annotate: bool,
+
+    // Reuse reports of unchanged content
+    #[arg(
+        long,
+        help = "Cache reports in .text-analyzer-cache, keyed by a hash of the content"
+    )]
+    cache: bool,
+
+    // Performance work on the streaming counters
+    #[arg(
+        long,
+        help = "Time each counter over the run and print a breakdown to stderr"
+    )]
+    profile: bool,
+
+    // Preprocessing
+    #[arg(
+        long,
+        value_name = "FORM",
+        help = "Normalize Unicode to NFC or NFD before counting (default: none)"
+    )]
+    unicode_norm: Option<UnicodeNorm>,
+
+    #[arg(
+        long,
+        help = "Analyze the visible text of HTML: drop tags, scripts and styles, decode entities"
+    )]
+    strip_html: bool,
+
+    #[arg(
+        long,
+        help = "Replace emails, phone and card numbers with [EMAIL], [PHONE] and [CARD] before anything is counted or printed"
+    )]
+    redact: bool,
+
+    #[arg(
+        long,
+        help = "Count only the text of markdown links and images, not their URLs or paths"
+    )]
+    markdown_links: bool,
+
+    #[arg(
+        long,
+        help = "Count fenced markdown code blocks and their lines, per language"
+    )]
+    code_blocks: bool,
+
+    #[arg(long, help = "Count markdown headings per level, H1 to H6")]
+    headings: bool,
+
+    #[arg(
+        long,
+        help = "Count markdown bullet and numbered list items and their nesting depth"
+    )]
+    lists: bool,
+
+    #[arg(
+        long,
+        help = "Collapse runs of spaces and tabs to one space and trim line ends before counting"
+    )]
+    compact_whitespace: bool,
+
+    #[arg(
+        long,
+        help = "Replace curly quotes, en and em dashes and ellipses by ASCII before counting"
+    )]
+    ascii_quotes: bool,
+
+    #[arg(
+        long,
+        help = "Cut runs of a repeated character (\"sooo\") to --squeeze-threshold before counting; \
+                this lowers char counts, whitespace is kept"
+    )]
+    squeeze: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = DEFAULT_SQUEEZE_THRESHOLD,
+        requires = "squeeze",
+        help = "Longest run of one character kept by --squeeze"
+    )]
+    squeeze_threshold: usize,
+
+    #[arg(
+        long,
+        help = "Fail if normalization changes the word count, printing both counts"
+    )]
+    verify_normalize: bool,
+
+    #[arg(
+        long = "grep",
+        value_name = "REGEX",
+        value_parser = parse_regex,
+        help = "Only count lines matching REGEX, and report how many matched"
+    )]
+    grep: Option<Regex>,
+
+    #[arg(
+        long,
+        requires = "grep",
+        help = "With --grep, count the lines not matching"
+    )]
+    invert: bool,
+
+    #[arg(
+        long,
+        value_name = "REGEX",
+        value_parser = parse_regex,
+        help = "Drop lines matching REGEX before counting (timestamps, boilerplate, comments)"
+    )]
+    ignore_lines: Option<Regex>,
+
+    // Word metrics
+    #[arg(short, long, help = "Fold case for word-based metrics")]
+    ignore_case: bool,
+
+    // Word boundaries, whitespace only by default
+    #[arg(
+        long,
+        help = "Also split words at hyphens for word counts and frequencies (\"well-known\" is 2 words)"
+    )]
+    split_hyphens: bool,
+
+    #[arg(
+        long,
+        help = "Also split words at en and em dashes (\"yes—no\" is 2 words)"
+    )]
+    split_dashes: bool,
+
+    // Optional metrics
+    #[arg(
+        long,
+        help = "Attribute lines to their last Git author and count words and lines per author"
+    )]
+    git_authors: bool,
+
+    #[arg(long, help = "Count emoji and list the distinct ones found")]
+    emoji: bool,
+
+    #[arg(
+        long,
+        help = "Count spaces, tabs, newlines and other whitespace separately"
+    )]
+    whitespace_breakdown: bool,
+
+    #[arg(
+        long,
+        help = "Count letters per Unicode script (Latin, Cyrillic, CJK, ...)"
+    )]
+    scripts: bool,
+
+    #[arg(
+        long,
+        help = "Guess the language of the text (ISO 639-3 code and confidence)"
+    )]
+    language: bool,
+
+    #[arg(
+        long,
+        requires = "language",
+        help = "With --language, guess it for each paragraph"
+    )]
+    language_per_paragraph: bool,
+
+    #[arg(
+        long,
+        help = "List words mixing scripts, like a Cyrillic \"а\" in a Latin word (homoglyph spoofing)"
+    )]
+    confusables: bool,
+
+    #[arg(long, help = "Count http(s) URLs")]
+    urls: bool,
+
+    #[arg(
+        long,
+        requires = "urls",
+        help = "With --urls, also list the unique URLs, ignoring host case and trailing slashes"
+    )]
+    unique_urls: bool,
+
+    #[arg(
+        long,
+        help = "Count sentences, questions, exclamations and period-terminated sentences"
+    )]
+    punctuation_stats: bool,
+
+    #[arg(
+        long,
+        value_name = "CHARS",
+        default_value = DEFAULT_SENTENCE_TERMINATORS,
+        help = "Characters ending a sentence, each one a boundary"
+    )]
+    sentence_terminators: String,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        value_parser = parse_abbreviations,
+        help = "More abbreviations after which a period does not end a sentence, one per line (Mr., Dr., e.g. are built in)"
+    )]
+    abbrev_file: Option<BTreeSet<String>>,
+
+    #[arg(
+        long,
+        value_name = "K",
+        help = "List the K longest sentences by word count"
+    )]
+    long_sentences: Option<usize>,
+
+    #[arg(long, help = "Show a histogram of the number of words per sentence")]
+    wps_dist: bool,
+
+    #[arg(
+        long,
+        value_name = "COLS",
+        help = "Length of the fullest histogram bar (default: fit the terminal width)"
+    )]
+    histogram_width: Option<usize>,
+
+    #[arg(long, help = "Count distinct sentences and list the repeated ones")]
+    duplicate_sentences: bool,
+
+    #[arg(
+        long,
+        help = "Count sentences starting with a capital and list those starting in lowercase"
+    )]
+    sentence_caps: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        default_missing_value = "3",
+        help = "List sentences of fewer than N words (default 3) as likely fragments"
+    )]
+    fragments: Option<usize>,
+
+    #[arg(
+        long,
+        help = "List sentences that look passive (\"was thrown\"); a heuristic with false positives"
+    )]
+    passive: bool,
+
+    #[arg(long, help = "Show the distribution of word-initial letters")]
+    initials: bool,
+
+    #[arg(
+        long,
+        help = "Show the distribution of the first character of each line, symbols and digits included"
+    )]
+    line_initials: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "List non stop-words appearing more than N times"
+    )]
+    overused: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Percentage of words that are stop words, high values suggest filler"
+    )]
+    stopword_ratio: bool,
+
+    #[arg(
+        long,
+        help = "Percentage of characters that are digits, high for numeric data"
+    )]
+    digit_ratio: bool,
+
+    #[arg(
+        long,
+        help = "Percentage of words written in all caps, single letters left out"
+    )]
+    caps_ratio: bool,
+
+    #[arg(
+        long,
+        help = "Compare the size of the text in UTF-8, UTF-16 and Latin-1 bytes"
+    )]
+    size_compare: bool,
+
+    #[arg(
+        long,
+        value_name = "WIDTH",
+        help = "Report how many lines the text takes once word-wrapped to WIDTH columns; \
+                the input is counted as is"
+    )]
+    reflow_words: Option<usize>,
+
+    #[arg(
+        long,
+        help = "List all-uppercase acronyms (NASA, MP3) with their frequency"
+    )]
+    acronyms: bool,
+
+    #[arg(long, help = "Count English contractions (don't, it's, you'll)")]
+    contractions: bool,
+
+    #[arg(long, help = "Count numeric tokens and report their sum and average")]
+    sum_numbers: bool,
+
+    #[arg(
+        long,
+        requires = "sum_numbers",
+        help = "List the malformed numbers, such as 1.2.3, skipped by --sum-numbers"
+    )]
+    warn_malformed: bool,
+
+    #[arg(long, help = "Count words inside double quotes, straight or curly")]
+    dialogue: bool,
+
+    #[arg(
+        long,
+        value_name = "K",
+        help = "Estimate the K most frequent words with a count-min sketch, in bounded memory; \
+                counts are approximate and may be slightly too high"
+    )]
+    approx_freq: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "K",
+        help = "List the K most frequent words of each file, in its own section"
+    )]
+    per_file_top: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Write the word,count table of all inputs to a CSV file, most frequent first"
+    )]
+    freq_csv: Option<PathBuf>,
+
+    #[arg(long, help = "List the words occurring exactly once (hapax legomena)")]
+    hapax: bool,
+
+    #[arg(
+        long,
+        value_name = "PREFIX",
+        help = "Count and list the words starting with PREFIX, case ignored"
+    )]
+    starts_with: Option<String>,
+
+    #[arg(
+        long,
+        help = "List lines in title case, every word capitalized but short function words"
+    )]
+    title_lines: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "List the phrases of N words seen more than once, a hint of boilerplate or copying"
+    )]
+    repeated_ngrams: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Report lexical diversity: type-token ratio, hapax ratio and root TTR (Guiraud)"
+    )]
+    diversity: bool,
+
+    #[arg(
+        long,
+        help = "Count sentences ending with a period, question mark, exclamation mark, ellipsis or another terminator"
+    )]
+    endings: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "List lines where a word appears more than N times, ignoring case"
+    )]
+    repeat_per_line: Option<usize>,
+
+    #[arg(long, help = "Show word count and reading time of each paragraph")]
+    paragraph_reading_time: bool,
+
+    #[arg(
+        long,
+        help = "Show the word count of each paragraph, with their mean and max"
+    )]
+    paragraph_words: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 200,
+        help = "Reading speed in words per minute"
+    )]
+    wpm: u32,
+
+    // Source code
+    #[arg(long, help = "Count code, comment and blank lines of source files")]
+    code_stats: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Lang::Rust,
+        requires = "code_stats",
+        help = "Comment syntax used by --code-stats"
+    )]
+    lang: Lang,
+
+    // Lints
+    #[arg(
+        long,
+        value_name = "N",
+        help = "List lines longer than N characters and exit with status 1 if any"
+    )]
+    max_line_length: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 4,
+        help = "Columns per tab stop when measuring line length"
+    )]
+    tab_width: usize,
+
+    #[arg(
+        long,
+        value_name = "BOUNDS",
+        value_delimiter = ',',
+        num_args = 0..=1,
+        default_missing_value = "40,80",
+        help = "Count lines per width range, bounds comma separated (default 40,80: 0-40, 41-80, 81+)"
+    )]
+    line_length_buckets: Option<Vec<usize>>,
+
+    #[arg(
+        long,
+        help = "Report trailing blank lines and whether the text ends with a newline"
+    )]
+    file_ending: bool,
+}
+
+#[derive(Args)]
+struct CompareArgs {
+    #[arg(value_name = "OLD", help = "Reference text file")]
+    old: PathBuf,
+
+    #[arg(value_name = "NEW", help = "Text file compared to OLD")]
+    new: PathBuf,
+
+    #[arg(long, help = "List words added in NEW and removed from OLD")]
+    diff_words: bool,
+
+    #[arg(short, long, help = "Fold case before comparing words")]
+    ignore_case: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value = "dark",
+        help = "Colors of the TUI, mono uses none"
+    )]
+    theme: ThemeName,
+}
+
+#[derive(Args)]
+struct MergeArgs {
+    #[arg(
+        value_name = "REPORT",
+        num_args = 1..,
+        required = true,
+        help = "JSON report files, single reports or arrays of per-file reports"
+    )]
+    reports: Vec<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = DEFAULT_PRECISION,
+        help = "Decimal places of averages"
+    )]
+    precision: usize,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum UnicodeNorm {
+    Nfc,
+    Nfd,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SortKey {
+    Name,
+    Words,
+    Chars,
+    Lines,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum EmptyCheck {
+    Words,
+    Bytes,
 }
 
-// *********    Type    **********
-type FunctionResult<T> = Result<T, Box<dyn std::error::Error>>;
+#[derive(Clone, Copy, Default, ValueEnum)]
+enum Lang {
+    #[default]
+    Rust,
+    Python,
+    Shell,
+}
 
-// ********* Structures **********
+impl Lang {
+    fn comments(self) -> CommentSyntax {
+        match self {
+            Lang::Rust => RUST_COMMENTS,
+            Lang::Python | Lang::Shell => HASH_COMMENTS,
+        }
+    }
+}
 
-#[derive(Debug, Eq, PartialEq)]
-struct Report {
-    char_count: usize,
-    word_count: usize,
-    line_count: usize,
+#[derive(Clone, Copy, ValueEnum)]
+enum GroupBy {
+    Extension,
+    Directory,
 }
 
 // ********* Functions **********
-fn read_input(file: Option<PathBuf>) -> FunctionResult<String> {
+fn read_input(file: Option<PathBuf>, stdin_timeout: Option<u64>) -> FunctionResult<String> {
     let mut content = String::new();
     if let Some(path) = file {
         // case 1 :  read specified file
@@ -115,6 +1100,9 @@ fn read_input(file: Option<PathBuf>) -> FunctionResult<String> {
         Ok(data)
     } else if !atty::is(Stream::Stdin) {
         // case 2 : stdin not a terminal, read from a pipe
+        if let Some(secs) = stdin_timeout {
+            return read_with_timeout(io::stdin(), Duration::from_secs(secs));
+        }
         io::stdin().read_to_string(&mut content)?;
         Ok(content)
     } else {
@@ -124,124 +1112,2901 @@ fn read_input(file: Option<PathBuf>) -> FunctionResult<String> {
     }
 }
 
-fn print_report(report: Report) -> FunctionResult<()> {
-    let mut terminal = init_terminal()?;
-
-    terminal.draw(|f| {
-        // Utilisation d'un block basique avec le titre
-        let size = f.size();
-        let block = ratatui::widgets::Block::default()
-            .title("Report")
-            .borders(ratatui::widgets::Borders::ALL);
-
-        f.render_widget(block, size);
+// Checked on the metadata, before anything is read
+fn check_file_sizes(files: &[PathBuf], args: &AnalyzeArgs) -> FunctionResult<()> {
+    let Some(limit) = args.max_file_size else {
+        return Ok(());
+    };
+    if args.force {
+        return Ok(());
+    }
+    for file in files {
+        let size = fs::metadata(file)?.len();
+        if size > limit {
+            return Err(format!(
+                "{} is {size} bytes, over --max-file-size {limit}; use --force to analyze it anyway",
+                file.display()
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
 
-        // Tu peux ajouter un Paragraph avec ton report ici
-        use ratatui::text::{Line, Span};
-        use ratatui::widgets::Paragraph;
+// Files picked from the --file arguments after directory expansion and filters
+fn selected_files(args: &AnalyzeArgs) -> FunctionResult<Vec<PathBuf>> {
+    let filter = PathFilter::new(&args.include, &args.exclude)?.with_max_depth(args.max_depth);
+    Ok(expand_inputs(&args.files, &filter)?)
+}
 
-        let lines = vec![
-            Line::from(vec![Span::raw(format!("Chars: {}", report.char_count))]),
-            Line::from(vec![Span::raw(format!("Words: {}", report.word_count))]),
-            Line::from(vec![Span::raw(format!("Lines: {}", report.line_count))]),
-        ];
-        let paragraph = Paragraph::new(lines);
+fn dry_run_lines(args: &AnalyzeArgs) -> FunctionResult<Vec<String>> {
+    if args.files.is_empty() {
+        return Ok(vec!["stdin".to_string()]);
+    }
+    Ok(selected_files(args)?
+        .iter()
+        .map(|file| file.display().to_string())
+        .collect())
+}
 
-        f.render_widget(paragraph, size);
-    })?;
+// Stdin first, then the files in order
+fn concat_inputs(stdin: Option<String>, files: &[PathBuf]) -> FunctionResult<String> {
+    let mut content = stdin.unwrap_or_default();
+    for file in files {
+        content.push_str(&read_input(Some(file.clone()), None)?);
+    }
+    Ok(content)
+}
 
-    // ⚠️ Si tu restaures direct, tu "casses" l'affichage.
-    // À ce stade, soit tu:
-    //  - attends un input utilisateur (genre touche q), soit
-    //  - tu fais un sleep pour voir le rendu avant le restore.
-    std::thread::sleep(std::time::Duration::from_secs(3));
+// Files are analyzed on the rayon pool; a reorder buffer holds each finished file
+// until every file before it is printed with `lines`, so the output comes in
+// path order. Returns the analyses, in that order too
+fn print_in_order<T: Send>(
+    paths: &[PathBuf],
+    analyze: impl Fn(&Path) -> FunctionResult<T> + Sync,
+    lines: impl Fn(&T) -> Vec<String>,
+    out: &mut impl Write,
+) -> FunctionResult<Vec<T>> {
+    let analyze = &analyze;
+    let (tx, rx) = mpsc::channel();
+    thread::scope(|scope| {
+        scope.spawn(move || {
+            paths
+                .par_iter()
+                .enumerate()
+                .for_each_with(tx, |tx, (index, path)| {
+                    // errors are not Send, they cross as text; the receiver is gone after one
+                    let _ = tx.send((index, analyze(path).map_err(|e| e.to_string())));
+                });
+        });
 
-    restore_terminal()?;
-    Ok(())
+        let mut pending = BTreeMap::new();
+        let mut done = Vec::with_capacity(paths.len());
+        for (index, result) in rx {
+            pending.insert(index, result);
+            while let Some(result) = pending.remove(&done.len()) {
+                let analyzed = result?;
+                for line in lines(&analyzed) {
+                    writeln!(out, "{line}")?;
+                }
+                out.flush()?;
+                done.push(analyzed);
+            }
+        }
+        Ok(done)
+    })
 }
 
-fn count_words(text: &str) -> usize {
-    text.split_whitespace().count()
+// --parallel: the files are analyzed on the rayon pool, and with plain
+// output their sections are printed as they come. The rest of the report,
+// totals included, is printed by the sequential path's code once all are done
+fn parallel_inputs(
+    args: &AnalyzeArgs,
+    selected: &SelectedMetrics,
+    out: &mut impl Write,
+) -> FunctionResult<Vec<Analyzed>> {
+    let mut paths = selected_files(args)?;
+    paths.sort();
+    let analyze_file = |path: &Path| -> FunctionResult<Analyzed> {
+        let (content, total_lines) = prepare(read_input(Some(path.to_path_buf()), None)?, args)?;
+        analyze_input(
+            Some(path.to_path_buf()),
+            &content,
+            total_lines,
+            args,
+            selected,
+            None,
+            None,
+        )
+    };
+    let sections = |analyzed: &Analyzed| -> Vec<String> {
+        if streams_sections(args, paths.len()) {
+            file_lines(&analyzed.file, args.banner, args.precision)
+        } else {
+            Vec::new()
+        }
+    };
+    print_in_order(&paths, analyze_file, sections, out)
 }
 
-fn count_char(text: &str) -> usize {
-    text.chars().count()
+// Whether --parallel prints each file's section before the others are done;
+// they are then the first lines of the plain multi-file report
+fn streams_sections(args: &AnalyzeArgs, files: usize) -> bool {
+    args.parallel
+        && files > 1
+        && args.format == Format::Plain
+        && args.columns.is_empty()
+        && args.template.is_none()
 }
 
-fn count_lines(text: &str) -> usize {
-    text.lines().count()
-}
+// Read on a background thread so a pipe that never closes cannot block us forever
+fn read_with_timeout<R: Read + Send + 'static>(
+    mut reader: R,
+    timeout: Duration,
+) -> FunctionResult<String> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut content = String::new();
+        let result = reader.read_to_string(&mut content).map(|_| content);
+        let _ = tx.send(result);
+    });
 
-fn analyze(text: &str) -> FunctionResult<Report> {
-    let mut report: Report = Report {
-        char_count: count_char(&text),
-        word_count: count_words(&text),
-        line_count: count_lines(&text),
-    };
-    Ok(report)
+    match rx.recv_timeout(timeout) {
+        Ok(result) => Ok(result?),
+        Err(_) => Err(format!(
+            "No EOF received on stdin within {} seconds (see --stdin-timeout)",
+            timeout.as_secs_f64()
+        )
+        .into()),
+    }
 }
 
-// *********      Test     **********
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::NamedTempFile;
+// Whatever arrived within `budget`, and whether reading was cut short before EOF
+fn read_within<R: Read + Send + 'static>(
+    mut reader: R,
+    budget: Duration,
+) -> FunctionResult<(String, bool)> {
+    let deadline = Instant::now() + budget;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buffer = [0u8; 8192];
+        loop {
+            let chunk = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => Ok(buffer[..read].to_vec()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => Err(e),
+            };
+            let failed = chunk.is_err();
+            // the receiver is gone once the budget is spent
+            if tx.send(chunk).is_err() || failed {
+                break;
+            }
+        }
+    });
 
-    #[test]
-    fn test_count_words() {
-        assert_eq!(count_words("lorem ipsum"), 2);
-        assert_eq!(count_words(""), 0);
-        assert_eq!(count_words("lorem ipsum^#~"), 2);
-        assert_eq!(count_words("lorem ipsu\n coucou"), 3);
-        assert_eq!(count_words("lorem   ipsum   "), 2);
-    }
+    let mut bytes = Vec::new();
+    let truncated = loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(left) {
+            Ok(chunk) => bytes.extend(chunk?),
+            Err(mpsc::RecvTimeoutError::Disconnected) => break false,
+            Err(mpsc::RecvTimeoutError::Timeout) => break true,
+        }
+    };
+    // a character cut in half by the deadline is dropped
+    let valid = match str::from_utf8(&bytes) {
+        Ok(text) => text.len(),
+        Err(e) if truncated && e.error_len().is_none() => e.valid_up_to(),
+        Err(e) => return Err(e.into()),
+    };
+    bytes.truncate(valid);
+    Ok((String::from_utf8(bytes)?, truncated))
+}
 
-    #[test]
-    fn test_count_char() {
-        assert_eq!(count_char(""), 0);
-        assert_eq!(count_char("abc"), 3);
-        assert_eq!(count_char(" abc "), 5);
-        assert_eq!(count_char("a.b.c"), 5);
-        assert_eq!(count_char("😊"), 1);
+// Shown until q or Esc is pressed; Tab, Shift-Tab and the number keys switch views
+fn print_report(mut views: Vec<TuiView>, theme: Theme) -> FunctionResult<()> {
+    let hint = if views.len() > 1 {
+        "Tab/Shift-Tab or 1-9 to switch views, q to quit"
+    } else {
+        "Press q to quit"
+    };
+    for view in &mut views {
+        view.lines.push(String::new());
+        view.lines.push(hint.to_string());
     }
 
-    #[test]
-    fn test_count_lines() {
-        assert_eq!(count_lines("1\n2\n2\n4"), 4);
-        assert_eq!(count_lines("1"), 1);
-        assert_eq!(count_lines(""), 0);
-    }
+    let mut terminal = init_terminal()?;
+    let mut selected = 0;
+    let result = (|| -> FunctionResult<()> {
+        loop {
+            terminal.draw(|f| tabbed_ui(f, &views, selected, theme))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Tab => selected = next_view(selected, views.len()),
+                KeyCode::BackTab => selected = previous_view(selected, views.len()),
+                KeyCode::Char(digit @ '1'..='9') => {
+                    let index = digit as usize - '1' as usize;
+                    if index < views.len() {
+                        selected = index;
+                    }
+                }
+                _ => {}
+            }
+        }
+    })();
+    restore_terminal()?;
+    result
+}
 
-    #[test]
-    fn test_analyze() -> FunctionResult<()> {
-        let text: &str = "aaa\naa";
-        let expected_report: Report = Report {
-            char_count: 6,
-            word_count: 2,
-            line_count: 2,
-        };
+// Most frequent first, ties alphabetical
+fn sorted_frequencies(frequencies: BTreeMap<String, usize>) -> Vec<(String, usize)> {
+    let mut frequencies: Vec<(String, usize)> = frequencies.into_iter().collect();
+    frequencies.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    frequencies
+}
 
-        assert_eq!(analyze(&text)?, expected_report);
-        Ok(())
-    }
+// The `k` most frequent words
+fn top_words(text: &str, args: &AnalyzeArgs, k: usize) -> Vec<(String, usize)> {
+    let mut frequencies =
+        sorted_frequencies(word_frequencies(&word_text(text, args), args.ignore_case));
+    frequencies.truncate(k);
+    frequencies
 }
 
-// ********* Main Function **********
+// Views of the report TUI besides the summary, in VIEW_TITLES order
+fn view_lines(text: &str, args: &AnalyzeArgs) -> [Vec<String>; 3] {
+    let mut frequent = vec!["Most frequent words:".to_string()];
+    frequent.extend(
+        top_words(text, args, TOP_WORDS)
+            .iter()
+            .map(|(word, count)| format!("  {word}: {count}")),
+    );
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
+    let widths: Vec<usize> = text
+        .lines()
+        .map(|line| line_width(line, args.tab_width))
+        .collect();
+    let longest = widths.iter().copied().max().unwrap_or(0);
+    let blank = text.lines().filter(|line| line.trim().is_empty()).count();
+    let line_stats = vec![
+        format!("Lines: {}", widths.len()),
+        format!("Blank lines: {blank}"),
+        format!("Longest line: {longest} columns"),
+        format!(
+            "Average line width: {}",
+            format_float(average(widths.iter().sum(), widths.len()), args.precision)
+        ),
+    ];
 
-    match cli.command {
-        Commands::Analyze { file } => {
-            let content: String = read_input(file)?;
+    let per_sentence = words_per_sentence(text, &args.sentence_terminators, abbreviations(args));
+    let readability = vec![
+        format!("Sentences: {}", per_sentence.len()),
+        format!(
+            "Average words per sentence: {}",
+            format_float(
+                average(per_sentence.iter().sum(), per_sentence.len()),
+                args.precision
+            )
+        ),
+        format!(
+            "Stop-word ratio: {}%",
+            format_float(stopword_ratio(text), args.precision)
+        ),
+        format!(
+            "Reading time ({} wpm): {}",
+            args.wpm,
+            format_duration(reading_time_secs(
+                count_words(&word_text(text, args)),
+                args.wpm
+            ))
+        ),
+    ];
+    [frequent, line_stats, readability]
+}
+
+// Redraws until q or Esc is pressed
+fn watch_report(args: &AnalyzeArgs) -> FunctionResult<()> {
+    let files = selected_files(args)?;
+    if files.is_empty() {
+        return Err("--watch needs at least one file".into());
+    }
+    let theme = Theme::new(args.theme);
+    let mut timer = RedrawTimer::new(Duration::from_millis(args.refresh_ms));
+    let mut times = modified_times(&files);
 
-            // analyze text
-            let report: Report = analyze(&content)?;
+    let mut terminal = init_terminal()?;
+    let result = (|| -> FunctionResult<()> {
+        loop {
+            if timer.poll(Instant::now()) {
+                let mut lines = watch_lines(&files, args)?;
+                lines.push(String::new());
+                lines.push("Watching for changes, press q to quit".to_string());
+                terminal.draw(|f| ui(f, &lines, theme))?;
+            }
+            if event::poll(Duration::from_millis(50))?
+                && let Event::Key(key) = event::read()?
+                && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+            {
+                return Ok(());
+            }
+            let current = modified_times(&files);
+            if current != times {
+                times = current;
+                timer.event();
+            }
+        }
+    })();
+    restore_terminal()?;
+    result
+}
 
-            // print result
-            print_report(report)?;
+// Cumulative report after every line, one compact JSON object each, flushed as it goes.
+// Each line is preprocessed on its own, and a line dropped by --grep or
+// --ignore-lines prints nothing
+fn stream_line_json(
+    mut reader: impl BufRead,
+    args: &AnalyzeArgs,
+    out: &mut impl Write,
+) -> FunctionResult<()> {
+    let mut accumulator = Accumulator::new();
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        let kept = preprocess(std::mem::take(&mut line), args);
+        if kept.is_empty() {
+            continue;
         }
+        accumulator.feed(&kept);
+        serde_json::to_writer(&mut *out, &accumulator.report())?;
+        out.write_all(b"\n")?;
+        out.flush()?;
     }
-
     Ok(())
 }
+
+// Run on every reload, so a file growing over --max-file-size stops the watch
+fn watch_lines(paths: &[PathBuf], args: &AnalyzeArgs) -> FunctionResult<Vec<String>> {
+    check_file_sizes(paths, args)?;
+    let metrics = metrics::select(&args.columns)?;
+    let mut files = Vec::new();
+    for path in paths {
+        let content = preprocess(read_input(Some(path.clone()), None)?, args);
+        let (report, extra) = analyze_content(&content, args, None, None)?;
+        let columns = metrics.iter().map(|m| m.compute(&report)).collect();
+        files.push(FileReport {
+            path: path.clone(),
+            report,
+            extra,
+            columns,
+        });
+    }
+    if !args.columns.is_empty() {
+        return Ok(columns_lines(&files, &args.columns, args.precision));
+    }
+    Ok(text_lines(
+        &files,
+        args.group_by,
+        args.banner,
+        args.precision,
+    ))
+}
+
+// One abbreviation per line, the final period optional, case ignored
+fn parse_abbreviations(path: &str) -> Result<BTreeSet<String>, String> {
+    let data = fs::read_to_string(path).map_err(|e| format!("cannot read {path}: {e}"))?;
+    Ok(data
+        .lines()
+        .map(|line| line.trim().trim_end_matches('.').to_lowercase())
+        .filter(|abbreviation| !abbreviation.is_empty())
+        .collect())
+}
+
+static NO_ABBREVIATIONS: BTreeSet<String> = BTreeSet::new();
+
+// Abbreviations of --abbrev-file, on top of the built-in ones
+fn abbreviations(args: &AnalyzeArgs) -> &BTreeSet<String> {
+    args.abbrev_file.as_ref().unwrap_or(&NO_ABBREVIATIONS)
+}
+
+fn sentence_count(text: &str, args: &AnalyzeArgs) -> usize {
+    count_sentences(text, &args.sentence_terminators, abbreviations(args))
+}
+
+fn parse_key_map(arg: &str) -> Result<KeyMap, String> {
+    let fields = json_fields();
+    let mut keys = KeyMap::new();
+    for pair in arg.split(',').filter(|pair| !pair.trim().is_empty()) {
+        let Some((from, to)) = pair.split_once('=') else {
+            return Err(format!("expected KEY=NAME, got \"{pair}\""));
+        };
+        let (from, to) = (from.trim(), to.trim());
+        if !fields.iter().any(|field| field == from) {
+            return Err(format!(
+                "unknown key \"{from}\", valid keys are: {}",
+                fields.join(", ")
+            ));
+        }
+        if to.is_empty() {
+            return Err(format!("empty new name for \"{from}\""));
+        }
+        keys.insert(from.to_string(), to.to_string());
+    }
+    Ok(keys)
+}
+
+fn parse_regex(arg: &str) -> Result<Regex, String> {
+    Regex::new(arg).map_err(|e| e.to_string())
+}
+
+// Byte count with an optional binary suffix: 512, 64K, 10M, 1G (a trailing B is allowed)
+fn parse_size(arg: &str) -> Result<u64, String> {
+    let upper = arg.trim().to_ascii_uppercase();
+    let digits = upper.strip_suffix('B').unwrap_or(&upper);
+    let (digits, factor) = match digits.char_indices().last() {
+        Some((i, 'K')) => (&digits[..i], 1 << 10),
+        Some((i, 'M')) => (&digits[..i], 1 << 20),
+        Some((i, 'G')) => (&digits[..i], 1 << 30),
+        _ => (digits, 1),
+    };
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(factor))
+        .ok_or_else(|| format!("Invalid size {arg:?}, expected e.g. 4096, 64K, 10M or 1G"))
+}
+
+fn parse_separator(arg: &str) -> Result<String, String> {
+    let mut separator = String::new();
+    let mut chars = arg.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            separator.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('0') => separator.push('\0'),
+            Some('t') => separator.push('\t'),
+            Some('n') => separator.push('\n'),
+            Some('\\') | None => separator.push('\\'),
+            Some(other) => {
+                separator.push('\\');
+                separator.push(other);
+            }
+        }
+    }
+
+    if separator.is_empty() {
+        Err("record separator cannot be empty".to_string())
+    } else {
+        Ok(separator)
+    }
+}
+
+fn report_lines(report: &Report, precision: usize) -> Vec<String> {
+    vec![
+        format!("Characters: {}", report.char_count),
+        format!("Characters (no spaces): {}", report.chars_no_spaces),
+        format!("Words: {}", report.word_count),
+        format!("Lines: {}", report.line_count),
+        format!("Unique lines: {}", report.unique_line_count),
+        format!(
+            "Average words per line: {}",
+            format_float(report.avg_words_per_line, precision)
+        ),
+    ]
+}
+
+const TEMPLATE_FIELDS: [&str; 7] = [
+    "file",
+    "chars",
+    "chars_no_spaces",
+    "words",
+    "lines",
+    "unique_lines",
+    "avg_words_per_line",
+];
+
+fn template_field(name: &str, file: &FileReport, precision: usize) -> Option<String> {
+    let report = &file.report;
+    match name {
+        "file" => Some(file.path.display().to_string()),
+        "chars" => Some(report.char_count.to_string()),
+        "chars_no_spaces" => Some(report.chars_no_spaces.to_string()),
+        "words" => Some(report.word_count.to_string()),
+        "lines" => Some(report.line_count.to_string()),
+        "unique_lines" => Some(report.unique_line_count.to_string()),
+        "avg_words_per_line" => Some(format_float(report.avg_words_per_line, precision)),
+        _ => None,
+    }
+}
+
+// Substitute {field} placeholders, {{ and }} being literal braces
+fn render_template(template: &str, file: &FileReport, precision: usize) -> FunctionResult<String> {
+    let mut output = String::new();
+    let mut rest = template;
+    while let Some(index) = rest.find(['{', '}']) {
+        output.push_str(&rest[..index]);
+        let tail = &rest[index..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            output.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+        let Some(end) = tail.find('}').filter(|_| tail.starts_with('{')) else {
+            return Err(format!("Unbalanced brace in template: {template}").into());
+        };
+        let name = &tail[1..end];
+        let Some(value) = template_field(name, file, precision) else {
+            return Err(format!(
+                "Unknown template field {{{name}}}, valid fields are: {}",
+                TEMPLATE_FIELDS.join(", ")
+            )
+            .into());
+        };
+        output.push_str(&value);
+        rest = &tail[end + 1..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+// Words listed in the Frequencies view of the TUI
+const TOP_WORDS: usize = 10;
+
+// 95.2 -> "1m 35s"
+fn format_duration(secs: f64) -> String {
+    let secs = secs.round() as u64;
+    if secs >= 60 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{secs}s")
+    }
+}
+
+// Alphabetical, or most frequent first with ties alphabetical
+fn vocabulary_lines(text: &str, ignore_case: bool, by_frequency: bool) -> Vec<String> {
+    if !by_frequency {
+        return vocabulary(text, ignore_case).into_iter().collect();
+    }
+    let mut frequencies: Vec<(String, usize)> =
+        word_frequencies(text, ignore_case).into_iter().collect();
+    frequencies.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    frequencies.into_iter().map(|(word, _)| word).collect()
+}
+
+// Numbers are right-aligned to the width of the last line number
+fn annotate_lines(text: &str) -> Vec<String> {
+    let width = count_lines(text).to_string().len();
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| format!("{:>width$} {line}", i + 1))
+        .collect()
+}
+
+fn profile_lines(profile: &Profile) -> Vec<String> {
+    [
+        ("chars", profile.chars),
+        ("words", profile.words),
+        ("lines", profile.lines),
+    ]
+    .iter()
+    .map(|(counter, time)| format!("{counter:<6} {:>10.3} ms", time.as_secs_f64() * 1000.0))
+    .collect()
+}
+
+fn indent(lines: Vec<String>, depth: usize) -> Vec<String> {
+    let pad = "  ".repeat(depth);
+    lines
+        .into_iter()
+        .map(|line| format!("{pad}{line}"))
+        .collect()
+}
+
+fn group_key(path: &Path, by: GroupBy) -> String {
+    match by {
+        GroupBy::Extension => path
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy()))
+            .unwrap_or_else(|| "(no extension)".to_string()),
+        GroupBy::Directory => match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.display().to_string(),
+            _ => ".".to_string(),
+        },
+    }
+}
+
+// Names of the empty inputs, which are dropped when `skip` is set
+fn filter_empty(files: &mut Vec<FileReport>, skip: bool) -> Vec<String> {
+    let empty = files
+        .iter()
+        .filter(|file| file.report.char_count == 0)
+        .map(FileReport::name)
+        .collect();
+    if skip {
+        files.retain(|file| file.report.char_count > 0);
+    }
+    empty
+}
+
+// Stable, so files comparing equal keep their command-line order
+fn sort_files(files: &mut [FileReport], key: SortKey, reverse: bool) {
+    files.sort_by(|a, b| {
+        let ordering = match key {
+            SortKey::Name => a.path.cmp(&b.path),
+            SortKey::Words => a.report.word_count.cmp(&b.report.word_count),
+            SortKey::Chars => a.report.char_count.cmp(&b.report.char_count),
+            SortKey::Lines => a.report.line_count.cmp(&b.report.line_count),
+        };
+        if reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+}
+
+fn group_reports(files: &[FileReport], by: GroupBy) -> BTreeMap<String, Vec<&FileReport>> {
+    let mut groups: BTreeMap<String, Vec<&FileReport>> = BTreeMap::new();
+    for file in files {
+        groups
+            .entry(group_key(&file.path, by))
+            .or_default()
+            .push(file);
+    }
+    groups
+}
+
+fn total<'a>(files: impl IntoIterator<Item = &'a FileReport>) -> Report {
+    let mut total = Report::default();
+    for file in files {
+        total += &file.report;
+    }
+    total
+}
+
+fn load_baseline(path: &Path) -> FunctionResult<Report> {
+    let data = fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read baseline {}: {e}", path.display()))?;
+    let not_single = |e: String| {
+        format!(
+            "Baseline {} is not a single JSON report: {e}",
+            path.display()
+        )
+    };
+    match parse_reports(&data).map_err(not_single)?.as_slice() {
+        [report] => Ok(report.clone()),
+        reports => Err(not_single(format!("it holds {} reports", reports.len())).into()),
+    }
+}
+
+// Counts every JSON report has had, unlike the later ones an older file may lack
+const REPORT_KEYS: [&str; 3] = ["char_count", "word_count", "line_count"];
+
+// A report file holds one report, or an array of them from a multi-file run,
+// either of them possibly under `files` next to the --with-metadata or
+// --checksum keys
+fn parse_reports(json: &str) -> Result<Vec<Report>, String> {
+    let entries = match serde_json::from_str(json).map_err(|e| e.to_string())? {
+        Value::Array(entries) => entries,
+        Value::Object(mut object) => match object.remove("files") {
+            Some(Value::Array(entries)) => entries,
+            _ => vec![Value::Object(object)],
+        },
+        _ => return Err("expected an object or an array".into()),
+    };
+    entries.into_iter().map(parse_report).collect()
+}
+
+fn parse_report(entry: Value) -> Result<Report, String> {
+    let Value::Object(mut object) = entry else {
+        return Err("a report is not an object".into());
+    };
+    if let Some(key) = REPORT_KEYS.iter().find(|&&key| !object.contains_key(key)) {
+        return Err(format!("missing {key}"));
+    }
+    // the file of a per-file report, the wrapper keys of a single one
+    for key in ["file", "generator", "version", "checksum"] {
+        object.remove(key);
+    }
+    serde_json::from_value(Value::Object(object)).map_err(|e| e.to_string())
+}
+
+// Sum of every report in `paths`, averages recomputed from the summed counts
+fn merge_reports(paths: &[PathBuf]) -> FunctionResult<Report> {
+    let mut total = Report::default();
+    for path in paths {
+        let data = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read report {}: {e}", path.display()))?;
+        let reports = parse_reports(&data)
+            .map_err(|e| format!("{} is not a JSON report: {e}", path.display()))?;
+        for report in &reports {
+            total += report;
+        }
+    }
+    Ok(total)
+}
+
+// Current minus baseline, one line per metric
+fn delta_lines(
+    current: &Report,
+    baseline: &Report,
+    precision: usize,
+) -> FunctionResult<Vec<String>> {
+    let (Value::Object(current), Value::Object(baseline)) = (
+        serde_json::to_value(current)?,
+        serde_json::to_value(baseline)?,
+    ) else {
+        return Err("reports do not serialize to objects".into());
+    };
+
+    let mut lines = vec!["Change from baseline:".to_string()];
+    for (key, value) in &current {
+        let old = baseline.get(key);
+        let delta = match (value.as_i64(), old.and_then(Value::as_i64)) {
+            (Some(new), Some(old)) => format!("{:+}", new - old),
+            _ => {
+                let new = value.as_f64().unwrap_or_default();
+                let old = old.and_then(Value::as_f64).unwrap_or_default();
+                format!("{:+.precision$}", new - old)
+            }
+        };
+        lines.push(format!("  {key}: {delta}"));
+    }
+    Ok(lines)
+}
+
+// Reports of every file below `dir`, with the same filters and preprocessing as the inputs
+fn directory_reports(dir: &Path, args: &AnalyzeArgs) -> FunctionResult<Vec<Report>> {
+    let filter = PathFilter::new(&args.include, &args.exclude)?.with_max_depth(args.max_depth);
+    let files = expand_inputs(&[dir.to_path_buf()], &filter)?;
+    if files.is_empty() {
+        return Err(format!("No files to compare to in {}", dir.display()).into());
+    }
+    files
+        .into_iter()
+        .map(|file| {
+            let content = preprocess(read_input(Some(file), None)?, args);
+            Ok(analyze_content(&content, args, None, None)?.0)
+        })
+        .collect()
+}
+
+// Current against the per-metric mean of `reference`, absolute and relative
+fn deviation_lines(
+    current: &Report,
+    reference: &[Report],
+    precision: usize,
+) -> FunctionResult<Vec<String>> {
+    let Value::Object(current) = serde_json::to_value(current)? else {
+        return Err("reports do not serialize to objects".into());
+    };
+    let reference = reference
+        .iter()
+        .map(serde_json::to_value)
+        .collect::<serde_json::Result<Vec<Value>>>()?;
+
+    let mut lines = vec![format!(
+        "Deviation from the average of {} files:",
+        reference.len()
+    )];
+    for (key, value) in &current {
+        let values = reference.iter().filter_map(|report| report[key].as_f64());
+        let mean = values.sum::<f64>() / reference.len().max(1) as f64;
+        let value = value.as_f64().unwrap_or_default();
+        let relative = if mean == 0.0 {
+            "n/a".to_string()
+        } else {
+            format!("{:+.1}%", (value - mean) / mean * 100.0)
+        };
+        lines.push(format!(
+            "  {key}: {} vs {}, {:+.precision$} ({relative})",
+            format_float(value, precision),
+            format_float(mean, precision),
+            value - mean
+        ));
+    }
+    Ok(lines)
+}
+
+fn banner_lines(file: &FileReport) -> Vec<String> {
+    let name = file
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| file.name());
+    banner(&name)
+}
+
+fn file_lines(file: &FileReport, with_banner: bool, precision: usize) -> Vec<String> {
+    let mut lines = if with_banner {
+        banner_lines(file)
+    } else {
+        Vec::new()
+    };
+    lines.push(file.path.display().to_string());
+    lines.extend(indent(report_lines(&file.report, precision), 1));
+    lines.extend(indent(file.extra.clone(), 1));
+    lines
+}
+
+fn multi_file_lines(
+    files: &[FileReport],
+    group_by: Option<GroupBy>,
+    with_banner: bool,
+    precision: usize,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    match group_by {
+        None => {
+            for file in files {
+                lines.extend(file_lines(file, with_banner, precision));
+            }
+        }
+        Some(by) => {
+            for (key, group) in group_reports(files, by) {
+                lines.push(format!("[{key}] {} file(s)", group.len()));
+                for file in &group {
+                    lines.extend(indent(file_lines(file, with_banner, precision), 1));
+                }
+                lines.push("  Subtotal".to_string());
+                lines.extend(indent(report_lines(&total(group), precision), 2));
+            }
+        }
+    }
+    lines.push(format!("Total ({} files)", files.len()));
+    lines.extend(indent(report_lines(&total(files), precision), 1));
+    lines
+}
+
+// Name and description of every registered metric, names aligned
+fn metric_list_lines() -> Vec<String> {
+    let registry = metrics::registry();
+    let width = registry
+        .iter()
+        .map(|metric| metric.name().len())
+        .max()
+        .unwrap_or(0);
+    registry
+        .iter()
+        .map(|metric| format!("{:<width$}  {}", metric.name(), metric.description()))
+        .collect()
+}
+
+fn word_list_lines(title: &str, words: &[String]) -> Vec<String> {
+    vec![format!("{title} ({}): {}", words.len(), words.join(" "))]
+}
+
+fn compare_lines(old: &str, new: &str, args: &CompareArgs) -> FunctionResult<Vec<String>> {
+    let mut lines = vec![args.old.display().to_string()];
+    lines.extend(indent(report_lines(&analyze(old)?, DEFAULT_PRECISION), 1));
+    lines.push(args.new.display().to_string());
+    lines.extend(indent(report_lines(&analyze(new)?, DEFAULT_PRECISION), 1));
+
+    if args.diff_words {
+        let (added, removed) = diff_words(old, new, args.ignore_case);
+        lines.extend(word_list_lines("Added", &added));
+        lines.extend(word_list_lines("Removed", &removed));
+    }
+    Ok(lines)
+}
+
+fn normalize_unicode(text: String, form: Option<UnicodeNorm>) -> String {
+    match form {
+        Some(UnicodeNorm::Nfc) => text.nfc().collect(),
+        Some(UnicodeNorm::Nfd) => text.nfd().collect(),
+        None => text,
+    }
+}
+
+// Kept lines, each newline terminated
+fn grep_lines(text: &str, pattern: &Regex, invert: bool) -> String {
+    let mut kept = String::new();
+    for line in text.lines().filter(|line| pattern.is_match(line) != invert) {
+        kept.push_str(line);
+        kept.push('\n');
+    }
+    kept
+}
+
+// The TUI is drawn from lines, every other output is printed as is
+enum Rendered {
+    Tui(Vec<String>),
+    Text(String),
+}
+
+fn lines_text(lines: &[String]) -> String {
+    lines.iter().map(|line| format!("{line}\n")).collect()
+}
+
+fn render_output(
+    files: &[FileReport],
+    args: &AnalyzeArgs,
+    delta: &[String],
+) -> FunctionResult<Rendered> {
+    // the TUI would hide the annotated text
+    let tui = args.format == Format::Tui && !args.annotate;
+    let plain = |mut lines: Vec<String>| {
+        if args.checksum {
+            let line = checksum_line(&lines_text(&lines));
+            lines.push(line);
+        }
+        lines
+    };
+    let text = |lines: Vec<String>| {
+        let lines = plain(lines);
+        if tui {
+            Rendered::Tui(lines)
+        } else {
+            Rendered::Text(lines_text(&lines))
+        }
+    };
+    let json = |json: String| -> serde_json::Result<Rendered> {
+        let json = if args.with_metadata {
+            with_metadata_json(&json)?
+        } else {
+            json
+        };
+        let json = if args.checksum {
+            with_checksum_json(&json)?
+        } else {
+            json
+        };
+        Ok(Rendered::Text(format!("{json}\n")))
+    };
+    let csv = |csv: String| {
+        let csv = if args.with_metadata {
+            with_metadata_csv(&csv)
+        } else {
+            csv
+        };
+        Rendered::Text(if args.checksum {
+            with_checksum_csv(&csv)
+        } else {
+            csv
+        })
+    };
+    let precision = args.precision;
+
+    if !args.columns.is_empty() {
+        let names = &args.columns;
+        return Ok(match args.format {
+            Format::Tui | Format::Plain => text(columns_lines(files, names, precision)),
+            Format::Json => json(columns_to_json(files, names, precision)?)?,
+            Format::Csv => csv(columns_to_csv(files, names, precision)),
+        });
+    }
+    if let Some(template) = &args.template {
+        let lines = files
+            .iter()
+            .map(|file| render_template(template, file, precision))
+            .collect::<FunctionResult<Vec<String>>>()?;
+        return Ok(Rendered::Text(lines_text(&plain(lines))));
+    }
+    Ok(match args.format {
+        Format::Tui | Format::Plain => {
+            let mut lines = text_lines(files, args.group_by, args.banner, precision);
+            lines.extend(delta.iter().cloned());
+            text(lines)
+        }
+        Format::Json => {
+            let keys = args.json_keys.clone().unwrap_or_default();
+            json(to_json(files, &keys, precision)?)?
+        }
+        Format::Csv => csv(to_csv(files, precision)),
+    })
+}
+
+// Writes `text` to `out`, and to the --tee file if any
+fn tee_output(
+    text: &str,
+    out: &mut impl Write,
+    tee: Option<&Path>,
+    append: bool,
+    header: bool,
+) -> FunctionResult<()> {
+    out.write_all(text.as_bytes())?;
+    if let Some(path) = tee {
+        write_tee(path, text, append, header)?;
+    }
+    Ok(())
+}
+
+// With `append`, the first line of `text` is taken as a `header` and skipped
+// when the file already has content
+fn write_tee(path: &Path, text: &str, append: bool, header: bool) -> FunctionResult<()> {
+    if !append {
+        fs::write(path, text)?;
+        return Ok(());
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let text = match text.split_once('\n') {
+        Some((_, rows)) if header && file.metadata()?.len() > 0 => rows,
+        _ => text,
+    };
+    file.write_all(text.as_bytes())?;
+    Ok(())
+}
+
+// Normalization steps, which should not change what is counted
+fn normalize(content: String, args: &AnalyzeArgs) -> String {
+    let content = normalize_unicode(content, args.unicode_norm);
+    let content = if args.ascii_quotes {
+        ascii_quotes(&content)
+    } else {
+        content
+    };
+    let content = if args.compact_whitespace {
+        compact_whitespace(&content)
+    } else {
+        content
+    };
+    if args.squeeze {
+        squeeze_repeats(&content, args.squeeze_threshold)
+    } else {
+        content
+    }
+}
+
+// Correctness guard for --verify-normalize
+fn verify_normalize(content: &str, args: &AnalyzeArgs) -> FunctionResult<()> {
+    let raw = count_words(content);
+    let normalized = count_words(&normalize(content.to_string(), args));
+    if raw != normalized {
+        return Err(format!(
+            "Normalization changed the word count: {raw} raw, {normalized} normalized"
+        )
+        .into());
+    }
+    Ok(())
+}
+
+// Transformations applied to the input before anything is counted
+fn preprocess(content: String, args: &AnalyzeArgs) -> String {
+    let content = if args.strip_html {
+        strip_html(&content)
+    } else {
+        content
+    };
+    let content = if args.markdown_links {
+        strip_markdown_links(&content)
+    } else {
+        content
+    };
+    let content = if args.redact {
+        redact(&content)
+    } else {
+        content
+    };
+    let content = normalize(content, args);
+    let content = match &args.ignore_lines {
+        Some(pattern) => grep_lines(&content, pattern, true),
+        None => content,
+    };
+    match &args.grep {
+        Some(pattern) => grep_lines(&content, pattern, args.invert),
+        None => content,
+    }
+}
+
+// Lines of the plain and TUI reports
+fn text_lines(
+    files: &[FileReport],
+    group_by: Option<GroupBy>,
+    with_banner: bool,
+    precision: usize,
+) -> Vec<String> {
+    if let [file] = files {
+        let mut lines = if with_banner {
+            banner_lines(file)
+        } else {
+            Vec::new()
+        };
+        lines.extend(report_lines(&file.report, precision));
+        lines.extend(file.extra.clone());
+        lines
+    } else {
+        // multi-file mode: one report per file plus totals
+        multi_file_lines(files, group_by, with_banner, precision)
+    }
+}
+
+// The text word counts and frequencies are taken from, see --split-hyphens
+fn word_text<'a>(content: &'a str, args: &AnalyzeArgs) -> Cow<'a, str> {
+    let mut boundaries = Vec::new();
+    if args.split_hyphens {
+        boundaries.extend_from_slice(HYPHENS);
+    }
+    if args.split_dashes {
+        boundaries.extend_from_slice(DASHES);
+    }
+    if boundaries.is_empty() {
+        Cow::Borrowed(content)
+    } else {
+        Cow::Owned(split_words_at(content, &boundaries))
+    }
+}
+
+// The registry metrics asked for with --columns and --exit-with
+struct SelectedMetrics {
+    columns: Vec<Box<dyn Metric>>,
+    exit: Option<Box<dyn Metric>>,
+}
+
+impl SelectedMetrics {
+    fn new(args: &AnalyzeArgs) -> FunctionResult<Self> {
+        Ok(SelectedMetrics {
+            columns: metrics::select(&args.columns)?,
+            exit: match &args.exit_with {
+                Some(name) => metrics::select(std::slice::from_ref(name))?.pop(),
+                None => None,
+            },
+        })
+    }
+}
+
+// One analyzed input, with what the exit status, --freq-csv and the TUI
+// views take from it
+struct Analyzed {
+    file: FileReport,
+    // --fail-on-empty or --max-line-length was hit
+    failed: bool,
+    exit_value: Option<MetricValue>,
+    frequencies: BTreeMap<String, usize>,
+    views: [Vec<String>; 3],
+}
+
+// Preprocessed input, with its line count from before --grep
+fn prepare(content: String, args: &AnalyzeArgs) -> FunctionResult<(String, usize)> {
+    if args.verify_normalize {
+        verify_normalize(&content, args)?;
+    }
+    let total_lines = count_lines(&content);
+    Ok((preprocess(content, args), total_lines))
+}
+
+// Everything computed from one prepared input, by the sequential and the
+// --parallel paths alike; `input` is None for stdin
+fn analyze_input(
+    input: Option<PathBuf>,
+    content: &str,
+    total_lines: usize,
+    args: &AnalyzeArgs,
+    selected: &SelectedMetrics,
+    cache: Option<&mut Cache>,
+    profile: Option<&mut Profile>,
+) -> FunctionResult<Analyzed> {
+    let (report, mut extra) = analyze_content(content, args, cache, profile)?;
+    if args.grep.is_some() {
+        let matched = count_lines(content);
+        extra.insert(0, format!("Matched lines: {matched} of {total_lines}"));
+    }
+    if args.git_authors {
+        let path = input
+            .as_ref()
+            .ok_or("--git-authors needs a file, not stdin")?;
+        extra.extend(author_lines(&author_reports(path)?));
+    }
+    let frequencies = match args.freq_csv {
+        Some(_) => word_frequencies(&word_text(content, args), args.ignore_case),
+        None => BTreeMap::new(),
+    };
+    let views = if args.format == Format::Tui {
+        view_lines(content, args)
+    } else {
+        Default::default()
+    };
+    Ok(Analyzed {
+        failed: lint_failed(content, args),
+        exit_value: selected.exit.as_ref().map(|metric| metric.compute(&report)),
+        frequencies,
+        views,
+        file: FileReport {
+            path: match input {
+                Some(path) => path,
+                #[cfg(feature = "http")]
+                None if args.url.is_some() => {
+                    PathBuf::from(args.url.as_deref().unwrap_or_default())
+                }
+                None if args.concat => PathBuf::from("concat"),
+                None => PathBuf::from("-"),
+            },
+            columns: selected
+                .columns
+                .iter()
+                .map(|m| m.compute(&report))
+                .collect(),
+            report,
+            extra,
+        },
+    })
+}
+
+// Core report plus the optional metrics requested on the command line
+// With a profile the counts go through a profiled `Accumulator`, whose
+// timings are added to `profile`
+fn analyze_content(
+    content: &str,
+    args: &AnalyzeArgs,
+    cache: Option<&mut Cache>,
+    profile: Option<&mut Profile>,
+) -> FunctionResult<(Report, Vec<String>)> {
+    let compute = || {
+        let mut report: Report = match profile {
+            Some(profile) => {
+                let mut accumulator = Accumulator::profiled();
+                accumulator.feed(content);
+                if let Some(timings) = accumulator.profile() {
+                    *profile += timings;
+                }
+                accumulator.report()
+            }
+            None => analyze(content)?,
+        };
+        if let Some(separator) = &args.record_sep {
+            report.line_count = count_records(content, separator);
+            report.unique_line_count = distinct_count(records(content, separator), args.trim_lines);
+        } else if args.trim_lines {
+            report.unique_line_count = distinct_count(content.lines(), true);
+        }
+        if args.split_hyphens || args.split_dashes {
+            report.word_count = count_words(&word_text(content, args));
+        }
+        report.update_averages();
+        Ok(report)
+    };
+    let report = match cache {
+        Some(cache) => {
+            let options = format!(
+                "record-sep={:?};trim-lines={};split-hyphens={};split-dashes={}",
+                args.record_sep, args.trim_lines, args.split_hyphens, args.split_dashes
+            );
+            cache.get_or_compute(&cache_key(content, &options), compute)?
+        }
+        None => compute()?,
+    };
+    Ok((report, extra_lines(content, args)))
+}
+
+const WPS_BUCKET_WIDTH: usize = 5;
+// Width assumed when stdout is not a terminal
+const FALLBACK_TERMINAL_WIDTH: usize = 80;
+// "  {range:>7} | " in front of every bar
+const HISTOGRAM_LABEL_WIDTH: usize = 12;
+
+// Terminal width minus the label and the count printed after the fullest bar
+fn default_histogram_width(buckets: &[(usize, usize)]) -> usize {
+    let columns = terminal::size().map_or(FALLBACK_TERMINAL_WIDTH, |(cols, _)| cols as usize);
+    let fullest = buckets.iter().map(|&(_, count)| count).max().unwrap_or(0);
+    let labels = HISTOGRAM_LABEL_WIDTH + 1 + fullest.to_string().len();
+    columns.saturating_sub(labels).max(1)
+}
+
+// One bar per bucket of `width` values, scaled so the fullest bucket is `bar_width` long
+fn histogram_lines(buckets: &[(usize, usize)], width: usize, bar_width: usize) -> Vec<String> {
+    let fullest = buckets.iter().map(|&(_, count)| count).max().unwrap_or(0);
+    buckets
+        .iter()
+        .map(|&(start, count)| {
+            let bar = (count * bar_width).div_ceil(fullest.max(1));
+            let range = format!("{start}-{}", start + width - 1);
+            format!("  {range:>7} | {} {count}", "#".repeat(bar))
+        })
+        .collect()
+}
+
+fn language_label(text: &str) -> String {
+    match detect_language(text) {
+        Some((code, confidence)) => format!("{code} (confidence {confidence:.2})"),
+        None => "unknown".to_string(),
+    }
+}
+
+fn extra_lines(text: &str, args: &AnalyzeArgs) -> Vec<String> {
+    let mut lines = Vec::new();
+    // split as the word count is, see --split-hyphens
+    let words = word_text(text, args);
+    if args.emoji {
+        lines.push(format!(
+            "Emoji: {} ({})",
+            count_emoji(text),
+            distinct_emoji(text).join(" ")
+        ));
+    }
+    if args.language {
+        lines.push(format!("Language: {}", language_label(text)));
+        if args.language_per_paragraph {
+            for (number, paragraph) in paragraphs(text).iter().enumerate() {
+                lines.push(format!(
+                    "  Paragraph {}: {}",
+                    number + 1,
+                    language_label(paragraph)
+                ));
+            }
+        }
+    }
+    if args.confusables {
+        let suspicious = mixed_script_words(text);
+        lines.push(format!("Mixed-script words: {}", suspicious.len()));
+        lines.extend(suspicious.iter().map(|word| format!("  {word}")));
+    }
+    if args.whitespace_breakdown {
+        let breakdown = whitespace_breakdown(text);
+        lines.push(format!("Spaces: {}", breakdown.spaces));
+        lines.push(format!("Tabs: {}", breakdown.tabs));
+        lines.push(format!("Newlines: {}", breakdown.newlines));
+        lines.push(format!("Other whitespace: {}", breakdown.other));
+    }
+    if args.scripts {
+        let breakdown: Vec<String> = script_breakdown(text)
+            .iter()
+            .map(|(script, count)| format!("{script}:{count}"))
+            .collect();
+        lines.push(format!("Scripts: {}", breakdown.join(" ")));
+    }
+    if let Some(n) = args.repeat_per_line {
+        let numbers: Vec<String> = lines_with_repeated_words(&words, n)
+            .iter()
+            .map(usize::to_string)
+            .collect();
+        lines.push(format!(
+            "Lines repeating a word more than {n} times: {}",
+            numbers.len()
+        ));
+        if !numbers.is_empty() {
+            lines.push(format!("  {}", numbers.join(", ")));
+        }
+    }
+    if args.title_lines {
+        let numbers: Vec<String> = title_case_lines(text)
+            .iter()
+            .map(usize::to_string)
+            .collect();
+        lines.push(format!("Title case lines: {}", numbers.len()));
+        if !numbers.is_empty() {
+            lines.push(format!("  {}", numbers.join(", ")));
+        }
+    }
+    if let Some(bounds) = &args.line_length_buckets {
+        let counts = line_length_buckets(text, bounds, args.tab_width);
+        let mut bounds = bounds.clone();
+        bounds.sort_unstable();
+        bounds.dedup();
+        lines.push("Line lengths:".to_string());
+        let mut start = 0;
+        for (index, count) in counts.iter().enumerate() {
+            let range = match bounds.get(index) {
+                Some(&end) => format!("{start}-{end}"),
+                None => format!("{start}+"),
+            };
+            lines.push(format!("  {range}: {count}"));
+            start = bounds.get(index).map_or(start, |end| end + 1);
+        }
+    }
+    if args.file_ending {
+        let ending = file_ending(text);
+        lines.push(format!(
+            "Trailing blank lines: {}",
+            ending.trailing_blank_lines
+        ));
+        lines.push(format!(
+            "Ends with newline: {}",
+            if ending.ends_with_newline {
+                "yes"
+            } else {
+                "no"
+            }
+        ));
+    }
+    if args.headings {
+        let counts: Vec<String> = heading_counts(text)
+            .iter()
+            .enumerate()
+            .map(|(level, count)| format!("H{}:{count}", level + 1))
+            .collect();
+        lines.push(format!("Headings: {}", counts.join(" ")));
+    }
+    if args.lists {
+        let stats = list_stats(text);
+        lines.push(format!("Bullet items: {}", stats.bullets));
+        lines.push(format!("Numbered items: {}", stats.numbered));
+        lines.push(format!("Deepest list nesting: {}", stats.max_depth));
+    }
+    if args.code_blocks {
+        let fences = code_blocks(text);
+        let total: usize = fences.blocks.iter().map(|block| block.lines).sum();
+        lines.push(format!(
+            "Code blocks: {} ({total} lines)",
+            fences.blocks.len()
+        ));
+        let mut languages: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        for block in &fences.blocks {
+            let language = if block.language.is_empty() {
+                "(none)"
+            } else {
+                &block.language
+            };
+            let entry = languages.entry(language).or_default();
+            entry.0 += 1;
+            entry.1 += block.lines;
+        }
+        for (language, (blocks, lines_in)) in languages {
+            lines.push(format!("  {language}: {blocks} blocks, {lines_in} lines"));
+        }
+        if let Some(line) = fences.unclosed {
+            lines.push(format!(
+                "  Warning: the code fence on line {line} is never closed"
+            ));
+        }
+    }
+    if args.code_stats {
+        let stats = code_stats(text, &args.lang.comments());
+        lines.push(format!("Code lines: {}", stats.code));
+        lines.push(format!("Comment lines: {}", stats.comment));
+        lines.push(format!("Blank lines: {}", stats.blank));
+    }
+    if args.urls {
+        lines.push(format!("URLs: {}", extract_urls(text).len()));
+        if args.unique_urls {
+            let unique = unique_urls(text);
+            lines.push(format!("Unique URLs: {}", unique.len()));
+            lines.extend(unique.iter().map(|url| format!("  {url}")));
+        }
+    }
+    if args.punctuation_stats {
+        lines.push(format!("Sentences: {}", sentence_count(text, args)));
+        let (terminators, abbreviations) = (&args.sentence_terminators, abbreviations(args));
+        let questions = count_questions(text, terminators, abbreviations);
+        let exclamations = count_exclamations(text, terminators, abbreviations);
+        let statements = count_statements(text, terminators, abbreviations);
+        lines.push(format!("Questions: {questions}"));
+        lines.push(format!("Exclamations: {exclamations}"));
+        lines.push(format!("Statements: {statements}"));
+    }
+    if let Some(k) = args.long_sentences {
+        lines.push("Longest sentences:".to_string());
+        for (sentence, words) in
+            long_sentences(text, k, &args.sentence_terminators, abbreviations(args))
+        {
+            lines.push(format!("  {words} words: {sentence}"));
+        }
+    }
+    if let Some(min_words) = args.fragments {
+        let fragments = sentence_fragments(
+            text,
+            min_words,
+            &args.sentence_terminators,
+            abbreviations(args),
+        );
+        lines.push(format!("Sentence fragments: {}", fragments.len()));
+        lines.extend(fragments.iter().map(|fragment| format!("  {fragment}")));
+    }
+    if args.sentence_caps {
+        let all = sentences(text, &args.sentence_terminators, abbreviations(args));
+        let lowercase = lowercase_sentences(text, &args.sentence_terminators, abbreviations(args));
+        let capitalized =
+            capitalized_sentence_count(text, &args.sentence_terminators, abbreviations(args));
+        lines.push(format!("Capitalized sentences: {capitalized}"));
+        lines.push(format!("Lowercase sentences: {}", lowercase.len()));
+        for number in lowercase {
+            lines.push(format!("  {number}: {}", all[number - 1]));
+        }
+    }
+    if args.duplicate_sentences {
+        let duplicates = duplicate_sentences(text, &args.sentence_terminators, abbreviations(args));
+        let distinct = unique_sentence_count(text, &args.sentence_terminators, abbreviations(args));
+        lines.push(format!("Distinct sentences: {distinct}"));
+        lines.push(format!("Duplicated sentences: {}", duplicates.len()));
+        for (sentence, count) in duplicates {
+            lines.push(format!("  {count}x {sentence}"));
+        }
+    }
+    if args.passive {
+        let passive = passive_sentences(text, &args.sentence_terminators, abbreviations(args));
+        lines.push(format!("Passive sentences: {}", passive.len()));
+        lines.extend(passive.iter().map(|sentence| format!("  {sentence}")));
+    }
+    if args.wps_dist {
+        let counts = words_per_sentence(&words, &args.sentence_terminators, abbreviations(args));
+        let buckets = bucket_counts(&counts, WPS_BUCKET_WIDTH);
+        let bar_width = args
+            .histogram_width
+            .unwrap_or_else(|| default_histogram_width(&buckets));
+        lines.push("Words per sentence:".to_string());
+        lines.extend(histogram_lines(&buckets, WPS_BUCKET_WIDTH, bar_width));
+    }
+    if args.initials {
+        let frequencies = initial_letter_frequencies(&words);
+        let distribution: Vec<String> = frequencies
+            .iter()
+            .map(|(letter, count)| format!("{letter}:{count}"))
+            .collect();
+        lines.push(format!("Initials: {}", distribution.join(" ")));
+        if let Some(letter) = most_common_initial(&frequencies) {
+            lines.push(format!("Most common initial: {letter}"));
+        }
+    }
+    if args.line_initials {
+        let distribution: Vec<String> = line_initial_frequencies(text)
+            .iter()
+            .map(|(c, count)| format!("{c}:{count}"))
+            .collect();
+        lines.push(format!("Line initials: {}", distribution.join(" ")));
+    }
+    if let Some(threshold) = args.overused {
+        let overused = overused_words(&words, threshold, args.ignore_case);
+        lines.push(format!(
+            "Overused words (more than {threshold} times): {}",
+            overused.len()
+        ));
+        for (word, count) in overused {
+            lines.push(format!("  {word}: {count}"));
+        }
+    }
+    if args.sum_numbers {
+        let found = numbers(text);
+        let sum: f64 = found.values.iter().sum();
+        lines.push(format!("Numbers: {}", found.values.len()));
+        lines.push(format!("  Sum: {}", format_float(sum, args.precision)));
+        let mean = sum / found.values.len().max(1) as f64;
+        lines.push(format!("  Average: {}", format_float(mean, args.precision)));
+        if args.warn_malformed && !found.malformed.is_empty() {
+            lines.push(format!(
+                "  Malformed numbers skipped: {}",
+                found.malformed.join(" ")
+            ));
+        }
+    }
+    if args.dialogue {
+        lines.push(format!("Words in quotes: {}", words_in_quotes(&words)));
+    }
+    if args.stopword_ratio {
+        lines.push(format!(
+            "Stop-word ratio: {}%",
+            format_float(stopword_ratio(&words), args.precision)
+        ));
+    }
+    if let Some(width) = args.reflow_words {
+        lines.push(format!(
+            "Lines reflowed to {width} columns: {}",
+            count_lines(&reflow(text, width))
+        ));
+    }
+    if args.size_compare {
+        let sizes = encoded_sizes(text);
+        lines.push("Encoded sizes:".to_string());
+        lines.push(format!("  UTF-8: {} bytes", sizes.utf8));
+        lines.push(format!("  UTF-16: {} bytes", sizes.utf16));
+        lines.push(format!(
+            "  Latin-1: {} bytes, {} characters not representable",
+            sizes.latin1, sizes.latin1_unrepresentable
+        ));
+    }
+    if args.digit_ratio {
+        lines.push(format!(
+            "Digit ratio: {}%",
+            format_float(digit_ratio(text), args.precision)
+        ));
+    }
+    if args.contractions {
+        lines.push(format!("Contractions: {}", count_contractions(text)));
+    }
+    if let Some(k) = args.approx_freq {
+        let mut top = TopWords::new(k, args.ignore_case);
+        top.feed(&words);
+        lines.push(format!("Approximate top {k} words:"));
+        for (word, count) in top.top() {
+            lines.push(format!("  {word}: ~{count}"));
+        }
+    }
+    if let Some(n) = args.repeated_ngrams {
+        let repeated = repeated_ngrams(&words, n, args.ignore_case);
+        lines.push(format!("Repeated {n}-grams: {}", repeated.len()));
+        for (ngram, count) in repeated {
+            lines.push(format!("  {ngram}: {count}"));
+        }
+    }
+    if let Some(prefix) = &args.starts_with {
+        let words = words_starting_with(&words, prefix);
+        lines.push(format!(
+            "Words starting with \"{prefix}\": {}",
+            words.values().sum::<usize>()
+        ));
+        for (word, count) in words {
+            lines.push(format!("  {word}: {count}"));
+        }
+    }
+    if args.hapax {
+        let hapaxes = hapax_legomena(&words, args.ignore_case);
+        lines.push(format!("Hapax legomena: {}", hapaxes.len()));
+        lines.extend(hapaxes.iter().map(|word| format!("  {word}")));
+    }
+    if args.endings {
+        let endings =
+            sentence_ending_breakdown(text, &args.sentence_terminators, abbreviations(args));
+        lines.push("Sentence endings:".to_string());
+        for (name, count) in [
+            ("Period", endings.period),
+            ("Question", endings.question),
+            ("Exclamation", endings.exclamation),
+            ("Ellipsis", endings.ellipsis),
+            ("Other", endings.other),
+            ("None", endings.unterminated),
+        ] {
+            lines.push(format!("  {name}: {count}"));
+        }
+    }
+    if args.diversity {
+        let diversity = lexical_diversity(&words, args.ignore_case);
+        lines.push("Lexical diversity:".to_string());
+        for (name, value) in [
+            ("Type-token ratio", diversity.type_token_ratio),
+            ("Hapax ratio", diversity.hapax_ratio),
+            ("Root TTR", diversity.root_ttr),
+        ] {
+            lines.push(format!("  {name}: {}", format_float(value, args.precision)));
+        }
+    }
+    if let Some(k) = args.per_file_top {
+        lines.push(format!("Top {k} words:"));
+        for (word, count) in top_words(text, args, k) {
+            lines.push(format!("  {word}: {count}"));
+        }
+    }
+    if args.caps_ratio {
+        lines.push(format!(
+            "All-caps words: {}%",
+            format_float(caps_ratio(&words), args.precision)
+        ));
+    }
+    if args.acronyms {
+        let acronyms = count_acronyms(text);
+        lines.push(format!("Acronyms: {}", acronyms.len()));
+        for (acronym, count) in acronyms {
+            lines.push(format!("  {acronym}: {count}"));
+        }
+    }
+    if args.paragraph_words {
+        let counts = paragraph_word_counts(&words);
+        let (mean, max) = paragraph_word_stats(&counts);
+        let counts: Vec<String> = counts.iter().map(usize::to_string).collect();
+        lines.push(format!("Paragraph words: {}", counts.join(" ")));
+        lines.push(format!(
+            "  Mean: {}, max: {max}",
+            format_float(mean, args.precision)
+        ));
+    }
+    if args.paragraph_reading_time {
+        lines.push(format!("Paragraph reading time ({} wpm):", args.wpm));
+        for (index, paragraph) in paragraphs(&words).iter().enumerate() {
+            let count = count_words(paragraph);
+            lines.push(format!(
+                "  {}: {count} words, {}",
+                index + 1,
+                format_duration(reading_time_secs(count, args.wpm))
+            ));
+        }
+        let count = count_words(&words);
+        lines.push(format!(
+            "  Total: {count} words, {}",
+            format_duration(reading_time_secs(count, args.wpm))
+        ));
+    }
+    if let Some(max) = args.max_line_length {
+        let long = long_lines(text, max, args.tab_width);
+        lines.push(format!("Lines over {max} characters: {}", long.len()));
+        for (number, width) in long {
+            lines.push(format!("  line {number}: {width} characters"));
+        }
+    }
+    lines
+}
+
+// Lints that should make the command exit with an error status
+fn lint_failed(text: &str, args: &AnalyzeArgs) -> bool {
+    let empty = match args.fail_on_empty {
+        Some(EmptyCheck::Words) => count_words(text) == 0,
+        Some(EmptyCheck::Bytes) => text.is_empty(),
+        None => false,
+    };
+    empty
+        || args
+            .max_line_length
+            .is_some_and(|max| !long_lines(text, max, args.tab_width).is_empty())
+}
+
+// Exit status for --exit-with; 256 words and more all exit with 255
+fn exit_code(values: &[MetricValue]) -> i32 {
+    let counts: usize = values
+        .iter()
+        .map(|value| match value {
+            MetricValue::Count(count) => *count,
+            MetricValue::Float(_) => 0,
+        })
+        .sum();
+    let floats: Vec<f64> = values
+        .iter()
+        .filter_map(|value| match value {
+            MetricValue::Float(value) => Some(*value),
+            MetricValue::Count(_) => None,
+        })
+        .collect();
+    let value = if floats.is_empty() {
+        counts as f64
+    } else {
+        floats.iter().sum::<f64>() / floats.len() as f64
+    };
+    value.clamp(0.0, 255.0) as i32
+}
+
+// ********* Main Function **********
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Commands::Analyze(args) => {
+            if args.dry_run {
+                for line in dry_run_lines(&args)? {
+                    println!("{line}");
+                }
+                return Ok(());
+            }
+            if args.watch {
+                return watch_report(&args);
+            }
+            if args.per_line_json {
+                let paths = selected_files(&args)?;
+                check_file_sizes(&paths, &args)?;
+                let mut out = io::stdout().lock();
+                return match paths.as_slice() {
+                    [] => stream_line_json(io::stdin().lock(), &args, &mut out),
+                    [path] => stream_line_json(BufReader::new(File::open(path)?), &args, &mut out),
+                    _ => Err(format!(
+                        "--per-line-json streams a single input, got {} files",
+                        paths.len()
+                    )
+                    .into()),
+                };
+            }
+            let mut failed = false;
+            let mut cache = if args.cache {
+                Some(Cache::load(Path::new(DEFAULT_CACHE_FILE))?)
+            } else {
+                None
+            };
+            let mut profile = args.profile.then(Profile::default);
+            let baseline = args.baseline.as_deref().map(load_baseline).transpose()?;
+            let selected = SelectedMetrics::new(&args)?;
+            let mut exit_values = Vec::new();
+
+            // no file means a single input read from stdin, --concat reads
+            // everything as a single input too
+            let inputs: Vec<Option<PathBuf>> = if args.parallel {
+                // read on the rayon pool instead, see parallel_inputs
+                Vec::new()
+            } else if args.files.is_empty() || args.concat {
+                vec![None]
+            } else {
+                selected_files(&args)?.into_iter().map(Some).collect()
+            };
+            check_file_sizes(&selected_files(&args)?, &args)?;
+
+            let mut analyses = if args.parallel {
+                parallel_inputs(&args, &selected, &mut io::stdout().lock())?
+            } else {
+                Vec::new()
+            };
+            for input in inputs {
+                #[cfg(feature = "http")]
+                let fetched = args.url.as_deref().map(fetch::fetch_url).transpose()?;
+                #[cfg(not(feature = "http"))]
+                let fetched: Option<String> = None;
+
+                let mut truncated = false;
+                let content = if let Some(body) = fetched {
+                    body
+                } else if let Some(ms) = args.time_budget
+                    && input.is_none()
+                    && !args.concat
+                    && !atty::is(Stream::Stdin)
+                {
+                    let (partial, cut) = read_within(io::stdin(), Duration::from_millis(ms))?;
+                    truncated = cut;
+                    partial
+                } else if args.concat {
+                    let stdin = if atty::is(Stream::Stdin) {
+                        None
+                    } else {
+                        Some(read_input(None, args.stdin_timeout)?)
+                    };
+                    concat_inputs(stdin, &selected_files(&args)?)?
+                } else {
+                    read_input(input.clone(), args.stdin_timeout)?
+                };
+                let (content, total_lines) = prepare(content, &args)?;
+                if args.dump_tokens {
+                    for token in tokens(&word_text(&content, &args)) {
+                        println!("{token}");
+                    }
+                    continue;
+                }
+                if args.vocabulary {
+                    let words = vocabulary_lines(
+                        &word_text(&content, &args),
+                        args.ignore_case,
+                        args.sort_by_frequency,
+                    );
+                    for word in words {
+                        println!("{word}");
+                    }
+                    continue;
+                }
+                if args.annotate {
+                    for line in annotate_lines(&content) {
+                        println!("{line}");
+                    }
+                }
+
+                // analyze text
+                let mut analyzed = analyze_input(
+                    input,
+                    &content,
+                    total_lines,
+                    &args,
+                    &selected,
+                    cache.as_mut(),
+                    profile.as_mut(),
+                )?;
+                if truncated {
+                    analyzed.file.extra.insert(
+                        0,
+                        format!(
+                            "Truncated: the {} ms time budget ran out, counts are partial",
+                            args.time_budget.unwrap_or_default()
+                        ),
+                    );
+                }
+                analyses.push(analyzed);
+            }
+
+            let mut files = Vec::new();
+            let mut views: [Vec<String>; 3] = Default::default();
+            let mut frequencies = BTreeMap::new();
+            let several = analyses.len() > 1;
+            for analyzed in analyses {
+                failed |= analyzed.failed;
+                exit_values.extend(analyzed.exit_value);
+                for (word, count) in analyzed.frequencies {
+                    *frequencies.entry(word).or_insert(0) += count;
+                }
+                for (view, lines) in views.iter_mut().zip(analyzed.views) {
+                    if several {
+                        view.push(format!("{}:", analyzed.file.name()));
+                        view.extend(indent(lines, 1));
+                    } else {
+                        view.extend(lines);
+                    }
+                }
+                files.push(analyzed.file);
+            }
+
+            if let Some(path) = &args.freq_csv {
+                write_frequency_csv(path, &sorted_frequencies(frequencies))?;
+            }
+            let empty = filter_empty(&mut files, args.skip_empty);
+            if args.warn_empty && !empty.is_empty() {
+                eprintln!("Warning: empty file(s): {}", empty.join(", "));
+            }
+            // word listings are printed while reading
+            let listing = args.dump_tokens || args.vocabulary;
+            if files.is_empty() && !listing {
+                return Err("No input left to analyze".into());
+            }
+
+            if let Some(key) = args.sort_files {
+                sort_files(&mut files, key, args.reverse);
+            }
+
+            // the change goes below text reports, and to stderr otherwise
+            let mut delta = match &baseline {
+                Some(baseline) => delta_lines(&total(&files), baseline, args.precision)?,
+                None => Vec::new(),
+            };
+            if let Some(dir) = &args.compare_to_dir {
+                let reference = directory_reports(dir, &args)?;
+                delta.extend(deviation_lines(&total(&files), &reference, args.precision)?);
+            }
+            let delta_in_text = !listing
+                && args.columns.is_empty()
+                && args.template.is_none()
+                && matches!(args.format, Format::Tui | Format::Plain);
+            if !delta_in_text {
+                for line in &delta {
+                    eprintln!("{line}");
+                }
+            }
+
+            // --parallel printed these as each file was done
+            let streamed: String = if streams_sections(&args, files.len()) {
+                files
+                    .iter()
+                    .map(|file| lines_text(&file_lines(file, args.banner, args.precision)))
+                    .collect()
+            } else {
+                String::new()
+            };
+
+            // print result, word listings were printed while reading
+            if !listing {
+                match render_output(&files, &args, &delta)? {
+                    Rendered::Tui(lines) => {
+                        if let Some(path) = &args.tee {
+                            write_tee(path, &lines_text(&lines), args.append, false)?;
+                        }
+                        let mut tabs = vec![TuiView {
+                            title: VIEW_TITLES[0],
+                            lines,
+                        }];
+                        tabs.extend(
+                            VIEW_TITLES[1..]
+                                .iter()
+                                .zip(views)
+                                .map(|(&title, lines)| TuiView { title, lines }),
+                        );
+                        print_report(tabs, Theme::new(args.theme))?
+                    }
+                    Rendered::Text(text) => match text.strip_prefix(streamed.as_str()) {
+                        // the file sections are out, the totals and the rest are not
+                        Some(rest) if !streamed.is_empty() => {
+                            io::stdout().write_all(rest.as_bytes())?;
+                            if let Some(path) = &args.tee {
+                                write_tee(path, &text, args.append, false)?;
+                            }
+                        }
+                        _ => tee_output(
+                            &text,
+                            &mut io::stdout(),
+                            args.tee.as_deref(),
+                            args.append,
+                            args.format == Format::Csv && args.template.is_none(),
+                        )?,
+                    },
+                }
+                if args.checksum
+                    && let Some(path) = &args.tee
+                {
+                    // the whole file, which is more than this run with --append
+                    let saved = fs::read_to_string(path)?;
+                    fs::write(sidecar_path(path), sidecar_line(path, &saved))?;
+                }
+            }
+
+            if let Some(cache) = &cache {
+                cache.save()?;
+            }
+            if let Some(profile) = &profile {
+                eprintln!("Profile:");
+                for line in profile_lines(profile) {
+                    eprintln!("  {line}");
+                }
+            }
+            if failed {
+                std::process::exit(1);
+            }
+            if selected.exit.is_some() {
+                io::stdout().flush()?;
+                std::process::exit(exit_code(&exit_values));
+            }
+        }
+        Commands::Compare(args) => {
+            let old = fs::read_to_string(&args.old)?;
+            let new = fs::read_to_string(&args.new)?;
+            print_report(
+                vec![TuiView {
+                    title: VIEW_TITLES[0],
+                    lines: compare_lines(&old, &new, &args)?,
+                }],
+                Theme::new(args.theme),
+            )?;
+        }
+        Commands::Merge(args) => {
+            let merged = FileReport {
+                path: PathBuf::from("merged"),
+                report: merge_reports(&args.reports)?,
+                extra: Vec::new(),
+                columns: Vec::new(),
+            };
+            println!("{}", to_json(&[merged], &KeyMap::new(), args.precision)?);
+        }
+        Commands::Metrics => {
+            for line in metric_list_lines() {
+                println!("{line}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// *********      Test     **********
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::time::Instant;
+    use tempfile::NamedTempFile;
+
+    // Arguments of `text-analyzer analyze <flags>`
+    fn parse_args(flags: &[&str]) -> FunctionResult<AnalyzeArgs> {
+        let cli = Cli::try_parse_from(["text-analyzer", "analyze"].iter().chain(flags))?;
+        match cli.command {
+            Commands::Analyze(args) => Ok(*args),
+            _ => Err("not analyze".into()),
+        }
+    }
+
+    fn file(path: impl Into<PathBuf>, text: &str) -> FunctionResult<FileReport> {
+        Ok(FileReport {
+            path: path.into(),
+            report: analyze(text)?,
+            extra: Vec::new(),
+            columns: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn test_parse_key_map() -> Result<(), String> {
+        let keys = parse_key_map("char_count=chars, word_count=words")?;
+        assert_eq!(keys["char_count"], "chars");
+        assert_eq!(keys["word_count"], "words");
+        assert!(parse_key_map("pages=p").is_err());
+        assert!(parse_key_map("char_count").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_separator() {
+        assert_eq!(parse_separator("\\0"), Ok("\0".to_string()));
+        assert_eq!(parse_separator("\\t"), Ok("\t".to_string()));
+        assert_eq!(parse_separator("--"), Ok("--".to_string()));
+        assert!(parse_separator("").is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("64k"), Ok(64 * 1024));
+        assert_eq!(parse_size("10M"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_size("1GB"), Ok(1 << 30));
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("ten").is_err());
+    }
+
+    #[test]
+    fn test_max_file_size() -> FunctionResult<()> {
+        let mut file = NamedTempFile::new()?;
+        file.write_all(&[b'a'; 2048])?;
+        let path = file.path().display().to_string();
+        let files = [file.path().to_path_buf()];
+
+        let limited = parse_args(&["-f", &path, "--max-file-size", "1K"])?;
+        let error = check_file_sizes(&files, &limited).unwrap_err();
+        assert!(error.to_string().contains("over --max-file-size 1024"));
+        // --watch checks again on every reload
+        assert!(watch_lines(&files, &limited).is_err());
+
+        let forced = parse_args(&["-f", &path, "--max-file-size", "1K", "--force"])?;
+        assert!(check_file_sizes(&files, &forced).is_ok());
+        let roomy = parse_args(&["-f", &path, "--max-file-size", "2K"])?;
+        assert!(check_file_sizes(&files, &roomy).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_reports() -> FunctionResult<()> {
+        let dir = tempfile::tempdir()?;
+        let mut files = Vec::new();
+        for (name, text) in [("a.md", "one two"), ("b.txt", "three"), ("c.md", "four")] {
+            let path = dir.path().join(name);
+            fs::write(&path, text)?;
+            files.push(file(path, text)?);
+        }
+
+        let groups = group_reports(&files, GroupBy::Extension);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(total(groups[".md"].iter().copied()).word_count, 3);
+        assert_eq!(total(groups[".txt"].iter().copied()).word_count, 1);
+
+        let groups = group_reports(&files, GroupBy::Directory);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(total(&files).word_count, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_unicode() -> FunctionResult<()> {
+        let composed = "caf\u{e9}".to_string();
+        let decomposed = "cafe\u{301}".to_string();
+        assert_ne!(analyze(&composed)?, analyze(&decomposed)?);
+
+        let nfc = |text: &String| normalize_unicode(text.clone(), Some(UnicodeNorm::Nfc));
+        assert_eq!(analyze(&nfc(&composed))?.char_count, 4);
+        assert_eq!(analyze(&nfc(&decomposed))?.char_count, 4);
+
+        let nfd = normalize_unicode(composed.clone(), Some(UnicodeNorm::Nfd));
+        assert_eq!(analyze(&nfd)?.char_count, 5);
+        assert_eq!(normalize_unicode(decomposed.clone(), None), decomposed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(30.0), "30s");
+        assert_eq!(format_duration(95.2), "1m 35s");
+        assert_eq!(format_duration(0.0), "0s");
+    }
+
+    #[test]
+    fn test_histogram_lines() {
+        let lines = histogram_lines(&[(0, 2), (5, 4)], 5, 10);
+        assert_eq!(lines[0], format!("      0-4 | {} 2", "#".repeat(5)));
+        assert_eq!(lines[1], format!("      5-9 | {} 4", "#".repeat(10)));
+    }
+
+    #[test]
+    fn test_filter_empty() -> FunctionResult<()> {
+        let dir = tempfile::tempdir()?;
+        let mut files = Vec::new();
+        for (name, text) in [
+            ("a.txt", "lorem"),
+            ("empty.txt", ""),
+            ("b.txt", "ipsum dolor"),
+        ] {
+            let path = dir.path().join(name);
+            fs::write(&path, text)?;
+            files.push(file(path, text)?);
+        }
+        let empty_name = dir.path().join("empty.txt").display().to_string();
+
+        // warning only: every file is kept
+        let mut kept = files.clone();
+        assert_eq!(filter_empty(&mut kept, false), vec![empty_name.clone()]);
+        assert_eq!(kept.len(), 3);
+
+        // skipping drops the file from the output and the totals
+        assert_eq!(filter_empty(&mut files, true), [empty_name]);
+        assert_eq!(files.len(), 2);
+        assert_eq!(total(&files).word_count, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_files() -> FunctionResult<()> {
+        let mut files = Vec::new();
+        for (name, text) in [
+            ("b.txt", "one two"),
+            ("a.txt", "one"),
+            ("c.txt", "one two three"),
+        ] {
+            files.push(file(name, text)?);
+        }
+        let names = |files: &[FileReport]| -> Vec<String> {
+            files
+                .iter()
+                .map(|file| file.path.display().to_string())
+                .collect()
+        };
+
+        sort_files(&mut files, SortKey::Words, true);
+        assert_eq!(names(&files), ["c.txt", "b.txt", "a.txt"]);
+
+        sort_files(&mut files, SortKey::Name, false);
+        assert_eq!(names(&files), ["a.txt", "b.txt", "c.txt"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_report_lines_precision() -> FunctionResult<()> {
+        let report = analyze("a b\nc d")?;
+        let average = |precision| report_lines(&report, precision).pop();
+        assert_eq!(average(3).as_deref(), Some("Average words per line: 2.000"));
+        assert_eq!(average(0).as_deref(), Some("Average words per line: 2"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_grep_lines() -> FunctionResult<()> {
+        let log = "INFO start\nERROR disk full\nWARN slow\nERROR timeout now\n";
+        let pattern = parse_regex("ERROR")?;
+
+        let errors = grep_lines(log, &pattern, false);
+        assert_eq!(errors, "ERROR disk full\nERROR timeout now\n");
+        let report = analyze(&errors)?;
+        assert_eq!(report.line_count, 2);
+        assert_eq!(report.word_count, 6);
+
+        assert_eq!(grep_lines(log, &pattern, true), "INFO start\nWARN slow\n");
+        assert!(parse_regex("(").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_warn_malformed() -> FunctionResult<()> {
+        let text = "prices 10 20 30 version 1.2.3";
+        let lines = extra_lines(text, &parse_args(&["--sum-numbers"])?);
+        assert!(lines.contains(&"  Sum: 60.00".to_string()));
+        assert!(lines.iter().all(|line| !line.contains("Malformed")));
+
+        let lines = extra_lines(text, &parse_args(&["--sum-numbers", "--warn-malformed"])?);
+        assert!(lines.contains(&"  Malformed numbers skipped: 1.2.3".to_string()));
+        assert!(parse_args(&["--warn-malformed"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_hyphens() -> FunctionResult<()> {
+        let text = "a well-known fact";
+        let (report, _) = analyze_content(text, &parse_args(&[])?, None, None)?;
+        assert_eq!(report.word_count, 3);
+        let (report, _) = analyze_content(text, &parse_args(&["--split-hyphens"])?, None, None)?;
+        assert_eq!(report.word_count, 4);
+        assert_eq!(report.char_count, 17);
+
+        // the other word metrics split the same way
+        let args = parse_args(&["--split-hyphens", "--paragraph-words", "--stopword-ratio"])?;
+        let lines = extra_lines(text, &args);
+        assert!(lines.contains(&"Paragraph words: 4".to_string()));
+        assert!(lines.contains(&"Stop-word ratio: 25.00%".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_lines() -> FunctionResult<()> {
+        let args = parse_args(&["--ignore-lines", "^#"])?;
+        let config = "# server settings\nport = 8080\n# host = old\nhost = example.org\n";
+
+        let kept = preprocess(config.to_string(), &args);
+        assert_eq!(kept, "port = 8080\nhost = example.org\n");
+        let report = analyze(&kept)?;
+        assert_eq!(report.line_count, 2);
+        assert_eq!(report.word_count, 6);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_reports() -> FunctionResult<()> {
+        let dir = tempfile::tempdir()?;
+        let single = dir.path().join("a.json");
+        let several = dir.path().join("b.json");
+        let files = [file("a.txt", "lorem ipsum\ndolor\n")?];
+        fs::write(&single, to_json(&files, &KeyMap::new(), DEFAULT_PRECISION)?)?;
+        let mut pair = files.to_vec();
+        pair.push(file("b.txt", "sit amet\n")?);
+        fs::write(&several, to_json(&pair, &KeyMap::new(), DEFAULT_PRECISION)?)?;
+
+        let merged = merge_reports(&[single, several])?;
+        assert_eq!(merged.word_count, 3 + 3 + 2);
+        assert_eq!(merged.line_count, 2 + 2 + 1);
+        assert_eq!(merged.char_count, 18 + 18 + 9);
+        assert_eq!(merged.avg_words_per_line, 8.0 / 5.0);
+
+        let broken = dir.path().join("broken.json");
+        fs::write(&broken, "{ not json")?;
+        let error = merge_reports(&[broken]).unwrap_err().to_string();
+        assert!(error.contains("broken.json is not a JSON report"));
+
+        // the reports under `files`, as --with-metadata and --checksum wrap them
+        let json = to_json(&pair, &KeyMap::new(), DEFAULT_PRECISION)?;
+        let wrapped = dir.path().join("wrapped.json");
+        fs::write(&wrapped, with_checksum_json(&with_metadata_json(&json)?)?)?;
+        assert_eq!(merge_reports(&[wrapped])?.word_count, 3 + 2);
+
+        // no counts at all, or under other names, is no report either
+        let empty = dir.path().join("empty.json");
+        fs::write(&empty, "{}")?;
+        let renamed = dir.path().join("renamed.json");
+        let keys = KeyMap::from([("word_count".to_string(), "words".to_string())]);
+        fs::write(&renamed, to_json(&files, &keys, DEFAULT_PRECISION)?)?;
+        for path in [empty, renamed] {
+            let error = merge_reports(std::slice::from_ref(&path)).unwrap_err();
+            assert!(
+                error
+                    .to_string()
+                    .contains(&format!("{} is not", path.display()))
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_deviation_from_directory() -> FunctionResult<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("a.txt"), "one two\n")?;
+        fs::write(dir.path().join("b.txt"), "one two three four\n")?;
+        let args = parse_args(&[])?;
+
+        let reference = directory_reports(dir.path(), &args)?;
+        assert_eq!(reference.len(), 2);
+        let current = analyze("a b c d e f\n")?;
+        let lines = deviation_lines(&current, &reference, 2)?;
+        assert_eq!(lines[0], "Deviation from the average of 2 files:");
+        assert!(lines.contains(&"  word_count: 6.00 vs 3.00, +3.00 (+100.0%)".to_string()));
+        assert!(lines.contains(&"  line_count: 1.00 vs 1.00, +0.00 (+0.0%)".to_string()));
+        // chars: 12 against (8 + 19) / 2
+        assert!(lines.contains(&"  char_count: 12.00 vs 13.50, -1.50 (-11.1%)".to_string()));
+
+        assert!(directory_reports(&dir.path().join("missing"), &args).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_baseline_delta() -> FunctionResult<()> {
+        let mut file = NamedTempFile::new()?;
+        file.write_all(br#"{"char_count": 10, "word_count": 5, "line_count": 1}"#)?;
+        let baseline = load_baseline(file.path())?;
+        assert_eq!(baseline.word_count, 5);
+        assert_eq!(baseline.unique_line_count, 0);
+
+        let current = analyze("lorem ipsum dolor\nsit")?;
+        let lines = delta_lines(&current, &baseline, 2)?;
+        assert!(lines.contains(&"  char_count: +11".to_string()));
+        assert!(lines.contains(&"  word_count: -1".to_string()));
+        assert!(lines.contains(&"  line_count: +1".to_string()));
+        assert!(lines.contains(&"  avg_words_per_line: +2.00".to_string()));
+
+        let mut empty = NamedTempFile::new()?;
+        empty.write_all(b"{}")?;
+        let error = load_baseline(empty.path()).unwrap_err().to_string();
+        assert!(error.contains(&format!(
+            "{} is not a single JSON report",
+            empty.path().display()
+        )));
+
+        let missing = load_baseline(Path::new("no/such/baseline.json")).unwrap_err();
+        assert!(missing.to_string().contains("Cannot read baseline"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_metric_list_lines() {
+        let lines = metric_list_lines();
+        assert!(lines.iter().any(|line| line.starts_with("words ")));
+        assert!(lines.iter().any(|line| line.starts_with("lines ")));
+        assert!(
+            lines
+                .iter()
+                .all(|line| line.len() > "avg_words_per_line  ".len())
+        );
+    }
+
+    #[test]
+    fn test_verify_normalize() -> FunctionResult<()> {
+        let args = parse_args(&[
+            "--compact-whitespace",
+            "--unicode-norm",
+            "nfd",
+            "--verify-normalize",
+        ])?;
+
+        verify_normalize("a   b\tc", &args)?;
+        assert_eq!(normalize("a   b\tc".to_string(), &args), "a b c");
+        Ok(())
+    }
+
+    #[test]
+    fn test_vocabulary_lines() {
+        let text = "banana Apple apple";
+        assert_eq!(vocabulary_lines(text, true, false), ["apple", "banana"]);
+        assert_eq!(
+            vocabulary_lines(text, false, false),
+            ["Apple", "apple", "banana"]
+        );
+        assert_eq!(
+            vocabulary_lines("b a c c b c", false, true),
+            ["c", "b", "a"]
+        );
+    }
+
+    #[test]
+    fn test_choose_layout() {
+        assert_eq!(choose_layout(80, 30), TuiLayout::Regular);
+        assert_eq!(choose_layout(COMPACT_WIDTH - 1, 10), TuiLayout::Compact);
+        // 80 columns leave 74 inside the margins and borders
+        assert_eq!(choose_layout(80, 74), TuiLayout::Regular);
+        assert_eq!(choose_layout(80, 75), TuiLayout::Compact);
+    }
+
+    #[test]
+    fn test_abbrev_file() -> FunctionResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("abbrev.txt");
+        fs::write(&path, "Capt.\n\n  Gen \n")?;
+        assert_eq!(
+            parse_abbreviations(&path.display().to_string())?,
+            BTreeSet::from(["capt".to_string(), "gen".to_string()])
+        );
+
+        let text = "Capt. Hook met Gen. Lee. Dr. Who too.";
+        let args = parse_args(&[])?;
+        assert_eq!(sentence_count(text, &args), 4);
+        let flag = path.display().to_string();
+        let args = parse_args(&[
+            "--abbrev-file",
+            &flag,
+            "--duplicate-sentences",
+            "--fragments=4",
+        ])?;
+        assert_eq!(sentence_count(text, &args), 2);
+
+        // every sentence output splits as the count does
+        let lines = extra_lines("Capt. Capt. Capt.\n", &args);
+        assert!(lines.contains(&"Distinct sentences: 1".to_string()));
+        assert!(lines.contains(&"Duplicated sentences: 0".to_string()));
+        assert!(lines.contains(&"  Capt. Capt. Capt.".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_columns_follow_options() -> FunctionResult<()> {
+        let args = parse_args(&[
+            "--record-sep",
+            ";",
+            "--split-hyphens",
+            "--columns",
+            "words,lines",
+        ])?;
+        let (report, _) = analyze_content("well-known;b c", &args, None, None)?;
+        let values: Vec<MetricValue> = metrics::select(&args.columns)?
+            .iter()
+            .map(|metric| metric.compute(&report))
+            .collect();
+        assert_eq!(report.word_count, 3);
+        assert_eq!(values, [MetricValue::Count(3), MetricValue::Count(2)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_exit_code() -> FunctionResult<()> {
+        let words = metrics::select(&["words".to_string()])?;
+        assert_eq!(exit_code(&[words[0].compute(&analyze("a b c")?)]), 3);
+        // the printed report's count, "well-known" being two words here
+        let args = parse_args(&["--exit-with", "words", "--split-hyphens"])?;
+        let (report, _) = analyze_content("well-known c", &args, None, None)?;
+        assert_eq!(exit_code(&[words[0].compute(&report)]), 3);
+        assert_eq!(
+            exit_code(&[MetricValue::Count(200), MetricValue::Count(100)]),
+            255
+        );
+        assert_eq!(
+            exit_code(&[MetricValue::Float(2.5), MetricValue::Float(4.0)]),
+            3
+        );
+        assert_eq!(exit_code(&[]), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_themes() {
+        let themes: Vec<Theme> = ThemeName::value_variants()
+            .iter()
+            .map(|&name| Theme::new(name))
+            .collect();
+        for (i, theme) in themes.iter().enumerate() {
+            assert!(themes[i + 1..].iter().all(|other| other != theme));
+        }
+        let mono = Theme::new(ThemeName::Mono);
+        assert_eq!(
+            (mono.text.fg, mono.text.bg, mono.accent.fg),
+            (None, None, None)
+        );
+    }
+
+    #[test]
+    fn test_tee_output() -> FunctionResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("report.json");
+        let files = [file("a.txt", "lorem ipsum")?];
+        let text = format!("{}\n", to_json(&files, &KeyMap::new(), DEFAULT_PRECISION)?);
+
+        let mut stdout = Vec::new();
+        tee_output(&text, &mut stdout, Some(&path), false, false)?;
+        assert_eq!(String::from_utf8(stdout)?, fs::read_to_string(&path)?);
+        assert!(fs::read_to_string(&path)?.contains("\"word_count\": 2"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_tee_append() -> FunctionResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("runs.csv");
+        for text in ["lorem ipsum", "dolor"] {
+            let files = [file("a.txt", text)?];
+            let csv = to_csv(&files, DEFAULT_PRECISION);
+            tee_output(&csv, &mut Vec::new(), Some(&path), true, true)?;
+        }
+
+        let written = fs::read_to_string(&path)?;
+        let lines: Vec<&str> = written.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("file,char_count"));
+        assert!(lines[1].starts_with("a.txt,11,"));
+        assert!(lines[2].starts_with("a.txt,5,"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_template() -> FunctionResult<()> {
+        let file = file("notes.txt", "a b c")?;
+
+        assert_eq!(render_template("{words}", &file, DEFAULT_PRECISION)?, "3");
+        assert_eq!(
+            render_template(
+                "{file}: {words} words, {lines} lines",
+                &file,
+                DEFAULT_PRECISION
+            )?,
+            "notes.txt: 3 words, 1 lines"
+        );
+        assert_eq!(
+            render_template("{{{chars}}}", &file, DEFAULT_PRECISION)?,
+            "{5}"
+        );
+
+        let error = render_template("{pages}", &file, DEFAULT_PRECISION)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains(
+            "file, chars, chars_no_spaces, words, lines, unique_lines, avg_words_per_line"
+        ));
+        assert!(render_template("{words", &file, DEFAULT_PRECISION).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_checksum_in_output() -> FunctionResult<()> {
+        let files = [file("a.txt", "lorem ipsum")?];
+        let rendered = |flags: &[&str]| -> FunctionResult<String> {
+            match render_output(&files, &parse_args(flags)?, &[])? {
+                Rendered::Text(text) => Ok(text),
+                Rendered::Tui(_) => Err("not text output".into()),
+            }
+        };
+
+        let plain = rendered(&["--format", "plain"])?;
+        let expected = format!("{plain}{}\n", checksum_line(&plain));
+        assert_eq!(rendered(&["--format", "plain", "--checksum"])?, expected);
+
+        let json = rendered(&["--format", "json"])?;
+        let checked: Value = serde_json::from_str(&rendered(&["--format", "json", "--checksum"])?)?;
+        assert_eq!(checked["checksum"], output::checksum(json.trim_end()));
+
+        let csv = rendered(&["--format", "csv"])?;
+        let expected = format!("{csv}# {}\n", checksum_line(&csv));
+        assert_eq!(rendered(&["--format", "csv", "--checksum"])?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_banner_only_in_plain_output() -> FunctionResult<()> {
+        let files = [file("notes.txt", "lorem ipsum")?];
+        let banner = banner("notes.txt");
+
+        let plain = text_lines(&files, None, true, DEFAULT_PRECISION);
+        assert_eq!(plain[..banner.len()], banner[..]);
+        assert!(!text_lines(&files, None, false, DEFAULT_PRECISION).contains(&banner[0]));
+
+        let json = to_json(&files, &KeyMap::new(), DEFAULT_PRECISION)?;
+        assert!(banner.iter().all(|row| !json.contains(row.as_str())));
+        assert!(!json.contains('#'));
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotate_lines() {
+        assert_eq!(annotate_lines("a\nb\nc"), ["1 a", "2 b", "3 c"]);
+
+        let text = "x\n".repeat(10);
+        let lines = annotate_lines(&text);
+        assert_eq!(lines[0], " 1 x");
+        assert_eq!(lines[9], "10 x");
+    }
+
+    #[test]
+    fn test_profile_keeps_report() -> FunctionResult<()> {
+        let text = "lorem ipsum\ndolor\nlorem ipsum\n";
+
+        let plain = analyze_content(text, &parse_args(&[])?, None, None)?;
+        let mut profile = Profile::default();
+        let profiled =
+            analyze_content(text, &parse_args(&["--profile"])?, None, Some(&mut profile))?;
+        assert_eq!(plain, profiled);
+        assert_eq!(profile_lines(&profile).len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dry_run_lines() -> FunctionResult<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("sub"))?;
+        for name in ["a.txt", "b.log", "sub/c.txt"] {
+            fs::write(dir.path().join(name), "lorem ipsum")?;
+        }
+        let root = dir.path().display().to_string();
+        let args = parse_args(&["--dry-run", "--exclude", "*.log", "-f", &root])?;
+
+        let lines = dry_run_lines(&args)?;
+        let expected: Vec<String> = ["a.txt", "sub/c.txt"]
+            .iter()
+            .map(|name| dir.path().join(name).display().to_string())
+            .collect();
+        assert_eq!(lines, expected);
+        // only paths, no report
+        assert!(lines.iter().all(|line| !line.contains("Words:")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_concat_inputs() -> FunctionResult<()> {
+        let mut first = NamedTempFile::new()?;
+        first.write_all(b"lorem ipsum\ndolor\n")?;
+        let mut second = NamedTempFile::new()?;
+        second.write_all(b"sit amet\n")?;
+        let paths = [first.path().to_path_buf(), second.path().to_path_buf()];
+
+        let combined = concat_inputs(Some("Header\n".to_string()), &paths)?;
+        assert!(combined.starts_with("Header\nlorem"));
+
+        let mut sum = analyze("Header\n")?;
+        sum += &analyze("lorem ipsum\ndolor\n")?;
+        sum += &analyze("sit amet\n")?;
+        assert_eq!(analyze(&combined)?, sum);
+        Ok(())
+    }
+
+    #[test]
+    fn test_frequency_csv() -> FunctionResult<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("freq.csv");
+        let frequencies = word_frequencies("b a c a b a The the", true);
+        write_frequency_csv(&path, &sorted_frequencies(frequencies))?;
+
+        let mut reader = csv::Reader::from_path(&path)?;
+        assert_eq!(reader.headers()?, vec!["word", "count"]);
+        let rows = reader
+            .records()
+            .map(|record| {
+                let record = record?;
+                Ok((record[0].to_string(), record[1].parse::<usize>()?))
+            })
+            .collect::<FunctionResult<Vec<_>>>()?;
+        assert_eq!(rows[0], ("a".to_string(), 3));
+        assert!(rows.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        assert_eq!(rows.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_per_file_top() -> FunctionResult<()> {
+        let dir = tempfile::tempdir()?;
+        let first = dir.path().join("first.txt");
+        let second = dir.path().join("second.txt");
+        fs::write(&first, "apple apple pear\n")?;
+        fs::write(&second, "plum kiwi plum plum\n")?;
+        let args = parse_args(&["--per-file-top", "1"])?;
+
+        let lines = watch_lines(&[first.clone(), second.clone()], &args)?;
+        let position = |line: &str| lines.iter().position(|l| l.trim() == line);
+        let (Some(first_at), Some(second_at)) = (
+            position(&first.display().to_string()),
+            position(&second.display().to_string()),
+        ) else {
+            return Err("missing file sections".into());
+        };
+        // each word listed once, under its own file
+        assert!(position("apple: 2").is_some_and(|i| first_at < i && i < second_at));
+        assert!(position("plum: 3").is_some_and(|i| second_at < i));
+        assert_eq!(position("Top 1 words:").map(|i| i > first_at), Some(true));
+        assert_eq!(
+            lines.iter().filter(|l| l.trim() == "Top 1 words:").count(),
+            2
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_input_file() -> FunctionResult<()> {
+        let mut file = NamedTempFile::new()?;
+        write!(file, "lorem ipsum")?;
+
+        assert_eq!(
+            read_input(Some(file.path().to_path_buf()), None)?,
+            "lorem ipsum"
+        );
+        Ok(())
+    }
+
+    // Never yields any data, like a pipe whose writer stays open
+    struct SlowReader;
+
+    impl Read for SlowReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(Duration::from_secs(5));
+            Ok(0)
+        }
+    }
+
+    #[test]
+    fn test_read_with_timeout() -> FunctionResult<()> {
+        let start = Instant::now();
+        assert!(read_with_timeout(SlowReader, Duration::from_millis(100)).is_err());
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        assert_eq!(
+            read_with_timeout(io::Cursor::new("lorem"), Duration::from_secs(1))?,
+            "lorem"
+        );
+        Ok(())
+    }
+
+    // An endless source, one word every 10 ms
+    struct TrickleReader;
+
+    impl Read for TrickleReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(Duration::from_millis(10));
+            let word = b"word ";
+            buf[..word.len()].copy_from_slice(word);
+            Ok(word.len())
+        }
+    }
+
+    #[test]
+    fn test_read_within() -> FunctionResult<()> {
+        let start = Instant::now();
+        let (partial, truncated) = read_within(TrickleReader, Duration::from_millis(200))?;
+        assert!(start.elapsed() < Duration::from_millis(500));
+        assert!(truncated);
+        let words = count_words(&partial);
+        assert!(words > 0 && words < 30, "{words} words");
+
+        let (all, truncated) = read_within(io::Cursor::new("lorem ipsum"), Duration::from_secs(1))?;
+        assert_eq!(all, "lorem ipsum");
+        assert!(!truncated);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fail_on_empty() -> FunctionResult<()> {
+        let words = parse_args(&["--fail-on-empty"])?;
+        assert!(lint_failed("", &words));
+        assert!(lint_failed(" \n", &words));
+        assert!(!lint_failed("hi", &words));
+
+        let bytes = parse_args(&["--fail-on-empty", "bytes"])?;
+        assert!(!lint_failed(" \n", &bytes));
+        assert!(lint_failed("", &bytes));
+
+        assert!(!lint_failed("", &parse_args(&[])?));
+        Ok(())
+    }
+
+    #[test]
+    fn test_print_in_order() -> FunctionResult<()> {
+        let paths: Vec<PathBuf> = (0..6).map(|i| PathBuf::from(i.to_string())).collect();
+        // the first files are the slowest, so they finish last
+        let slow_first = |path: &Path| -> FunctionResult<Vec<String>> {
+            let index: u64 = path.display().to_string().parse()?;
+            thread::sleep(Duration::from_millis((6 - index) * 15));
+            Ok(vec![format!("file {index}")])
+        };
+        let mut out = Vec::new();
+        let printed = |lines: &Vec<String>| lines.clone();
+        let done = print_in_order(&paths, slow_first, printed, &mut out)?;
+        assert_eq!(done.len(), 6);
+        assert_eq!(
+            String::from_utf8(out)?,
+            "file 0\nfile 1\nfile 2\nfile 3\nfile 4\nfile 5\n"
+        );
+
+        let failing = |path: &Path| -> FunctionResult<Vec<String>> {
+            Err(format!("cannot read {}", path.display()).into())
+        };
+        let error = print_in_order(&paths, failing, printed, &mut Vec::new()).err();
+        assert_eq!(
+            error.map(|e| e.to_string()),
+            Some("cannot read 0".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parallel_inputs() -> FunctionResult<()> {
+        let dir = tempfile::tempdir()?;
+        for (name, text) in [("b.txt", "dolor sit amet"), ("a.txt", "lorem ipsum")] {
+            fs::write(dir.path().join(name), text)?;
+        }
+        let root = dir.path().display().to_string();
+        let run = |flags: &[&str]| -> FunctionResult<(Vec<Analyzed>, String)> {
+            let args = parse_args(&[&["--parallel", "-f", &root], flags].concat())?;
+            let mut out = Vec::new();
+            let analyses = parallel_inputs(&args, &SelectedMetrics::new(&args)?, &mut out)?;
+            Ok((analyses, String::from_utf8(out)?))
+        };
+
+        // the sections printed early are the head of the plain report
+        let (analyses, printed) = run(&["--format", "plain"])?;
+        let files: Vec<FileReport> = analyses.into_iter().map(|a| a.file).collect();
+        let report = lines_text(&text_lines(&files, None, false, DEFAULT_PRECISION));
+        assert!(printed.starts_with(&format!("{}\n", files[0].path.display())));
+        assert!(
+            report
+                .strip_prefix(&printed)
+                .is_some_and(|rest| rest.starts_with("Total"))
+        );
+
+        // json and csv wait for the whole set
+        let (analyses, printed) = run(&["--format", "json"])?;
+        assert_eq!(analyses.len(), 2);
+        assert!(printed.is_empty());
+
+        // the lint result is kept for the exit status
+        let (analyses, _) = run(&["--format", "plain", "--max-line-length", "3"])?;
+        assert!(analyses.iter().all(|analyzed| analyzed.failed));
+        let (analyses, _) = run(&["--format", "plain", "--exit-with", "words"])?;
+        let values: Vec<_> = analyses.iter().filter_map(|a| a.exit_value).collect();
+        assert_eq!(exit_code(&values), 5);
+
+        assert!(parse_args(&["--parallel", "-f", &root, "--sort-files", "words"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_line_json() -> FunctionResult<()> {
+        let mut out = Vec::new();
+        let args = parse_args(&["--per-line-json"])?;
+        stream_line_json("lorem\nipsum dolor\nsit\n".as_bytes(), &args, &mut out)?;
+        let reports = String::from_utf8(out)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<Report>, _>>()?;
+
+        assert_eq!(reports.len(), 3);
+        let words: Vec<usize> = reports.iter().map(|r| r.word_count).collect();
+        assert_eq!(words, [1, 3, 4]);
+        assert!(
+            reports
+                .windows(2)
+                .all(|pair| pair[0].char_count < pair[1].char_count
+                    && pair[0].line_count < pair[1].line_count)
+        );
+
+        // the lines as the other outputs would see them
+        let mut out = Vec::new();
+        let args = parse_args(&["--per-line-json", "--ignore-lines", "^#", "--redact"])?;
+        let text = "# note\nmail bob@example.org\n";
+        stream_line_json(text.as_bytes(), &args, &mut out)?;
+        let out = String::from_utf8(out)?;
+        let reports = out
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<Report>, _>>()?;
+        let expected = analyze(&preprocess(text.to_string(), &args))?;
+        assert_eq!(reports, [expected]);
+        assert!(parse_args(&["--per-line-json", "--record-sep", ";"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_view_wraparound() {
+        let count = VIEW_TITLES.len();
+        assert_eq!(next_view(0, count), 1);
+        assert_eq!(next_view(count - 1, count), 0);
+        assert_eq!(previous_view(0, count), count - 1);
+        assert_eq!(previous_view(2, count), 1);
+        assert_eq!(next_view(0, 1), 0);
+        assert_eq!(previous_view(0, 0), 0);
+    }
+}
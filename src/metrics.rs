@@ -0,0 +1,161 @@
+/*!
+ * Registry of the metrics selectable by name with `--columns`.
+ *
+ * Notes:
+ *  - a metric is anything implementing `Metric`, registered in `registry`
+ *  - names match the template fields of the CLI
+ *  - values are read from a computed `Report`, so they follow the options it was built with
+ */
+
+use crate::Report;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum MetricValue {
+    Count(usize),
+    Float(f64),
+}
+
+impl MetricValue {
+    pub fn format(&self, precision: usize) -> String {
+        match self {
+            MetricValue::Count(count) => count.to_string(),
+            MetricValue::Float(value) => format!("{value:.precision$}"),
+        }
+    }
+}
+
+// Sync so that --parallel can share the selected metrics across threads
+pub trait Metric: Sync {
+    fn name(&self) -> &str;
+    // One line, shown by the metrics command
+    fn description(&self) -> &str;
+    fn compute(&self, report: &Report) -> MetricValue;
+}
+
+// A metric backed by one of the counting functions
+struct Builtin {
+    name: &'static str,
+    description: &'static str,
+    compute: fn(&Report) -> MetricValue,
+}
+
+impl Metric for Builtin {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn description(&self) -> &str {
+        self.description
+    }
+
+    fn compute(&self, report: &Report) -> MetricValue {
+        (self.compute)(report)
+    }
+}
+
+fn builtin(
+    name: &'static str,
+    description: &'static str,
+    compute: fn(&Report) -> MetricValue,
+) -> Box<dyn Metric> {
+    Box::new(Builtin {
+        name,
+        description,
+        compute,
+    })
+}
+
+/// Every available metric, in display order.
+pub fn registry() -> Vec<Box<dyn Metric>> {
+    vec![
+        builtin("chars", "Characters, whitespace included", |report| {
+            MetricValue::Count(report.char_count)
+        }),
+        builtin(
+            "chars_no_spaces",
+            "Characters other than whitespace",
+            |report| MetricValue::Count(report.chars_no_spaces),
+        ),
+        builtin("words", "Whitespace-separated words", |report| {
+            MetricValue::Count(report.word_count)
+        }),
+        builtin(
+            "lines",
+            "Lines, the last one counted even without a newline",
+            |report| MetricValue::Count(report.line_count),
+        ),
+        builtin("unique_lines", "Distinct lines", |report| {
+            MetricValue::Count(report.unique_line_count)
+        }),
+        builtin("avg_words_per_line", "Words divided by lines", |report| {
+            MetricValue::Float(report.avg_words_per_line)
+        }),
+    ]
+}
+
+/// The metrics named in `names`, in that order.
+pub fn select(names: &[String]) -> Result<Vec<Box<dyn Metric>>, String> {
+    names
+        .iter()
+        .map(|name| {
+            registry()
+                .into_iter()
+                .find(|metric| metric.name() == name)
+                .ok_or_else(|| {
+                    let valid: Vec<String> = registry()
+                        .iter()
+                        .map(|metric| metric.name().to_string())
+                        .collect();
+                    format!(
+                        "Unknown metric {name}, valid metrics are: {}",
+                        valid.join(", ")
+                    )
+                })
+        })
+        .collect()
+}
+
+// *********      Test     **********
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FunctionResult, analyze};
+
+    #[test]
+    fn test_registry_matches_analyze() -> FunctionResult<()> {
+        let text = "lorem ipsum\ndolor sit amet\nlorem ipsum";
+        let report = analyze(text)?;
+        let value = |name: &str| -> FunctionResult<MetricValue> {
+            Ok(select(&[name.to_string()])?[0].compute(&report))
+        };
+
+        assert_eq!(value("words")?, MetricValue::Count(report.word_count));
+        assert_eq!(value("lines")?, MetricValue::Count(report.line_count));
+        assert_eq!(value("unique_lines")?, MetricValue::Count(2));
+        assert_eq!(
+            value("avg_words_per_line")?,
+            MetricValue::Float(report.avg_words_per_line)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_select() -> FunctionResult<()> {
+        let names = ["lines".to_string(), "words".to_string()];
+        let selected: Vec<String> = select(&names)?
+            .iter()
+            .map(|metric| metric.name().to_string())
+            .collect();
+        assert_eq!(selected, names);
+
+        let error = select(&["pages".to_string()])
+            .err()
+            .ok_or("pages is no metric")?;
+        assert!(error.contains("valid metrics are: chars, chars_no_spaces, words"));
+        assert_eq!(MetricValue::Float(2.0).format(1), "2.0");
+        assert_eq!(MetricValue::Count(3).format(1), "3");
+        Ok(())
+    }
+}
@@ -0,0 +1,609 @@
+/*!
+ * Output formats of the analyze command besides the TUI report.
+ *
+ * Notes:
+ *  - plain prints the same lines as the TUI, json and csv only carry the counts
+ *  - the banner font is built in, 5 rows high
+ */
+
+use clap::ValueEnum;
+use serde::{Serialize, Serializer, ser::Error};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+use text_analyzer::{Report, metrics::MetricValue};
+
+// Decimal places of floating point metrics, see --precision
+pub const DEFAULT_PRECISION: usize = 2;
+
+// Output key -> replacement, see --json-keys
+pub type KeyMap = BTreeMap<String, String>;
+
+#[derive(Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum Format {
+    #[default]
+    Tui,
+    Plain,
+    Json,
+    Csv,
+}
+
+// One analyzed input, a file or stdin ("-")
+#[derive(Clone)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub report: Report,
+    pub extra: Vec<String>,
+    // values of the metrics selected with --columns
+    pub columns: Vec<MetricValue>,
+}
+
+impl FileReport {
+    pub fn name(&self) -> String {
+        if self.path == Path::new("-") {
+            "stdin".to_string()
+        } else {
+            self.path.display().to_string()
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct FileEntry<'a> {
+    file: String,
+    #[serde(flatten)]
+    report: &'a Report,
+}
+
+// Keys a JSON report can contain
+pub fn json_fields() -> Vec<String> {
+    let mut fields = vec!["file".to_string()];
+    if let Ok(Value::Object(report)) = serde_json::to_value(Report::default()) {
+        fields.extend(report.keys().cloned());
+    }
+    fields
+}
+
+pub fn format_float(value: f64, precision: usize) -> String {
+    format!("{value:.precision$}")
+}
+
+fn round_floats(value: Value, precision: usize) -> Value {
+    match value {
+        Value::Number(number) if number.is_f64() => {
+            let factor = 10f64.powi(precision as i32);
+            let rounded = number.as_f64().map(|x| (x * factor).round() / factor);
+            rounded.map_or(Value::Number(number), Value::from)
+        }
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(key, value)| (key, round_floats(value, precision)))
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .map(|value| round_floats(value, precision))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+fn rename_keys(value: Value, keys: &KeyMap) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = keys.get(&key).cloned().unwrap_or(key);
+                    (key, rename_keys(value, keys))
+                })
+                .collect::<Map<String, Value>>(),
+        ),
+        Value::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .map(|value| rename_keys(value, keys))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+// Serializes `value` with its object keys renamed, keeping their order,
+// and its floats rounded to `precision` decimals
+struct RenamedKeys<'a, T> {
+    value: &'a T,
+    keys: &'a KeyMap,
+    precision: usize,
+}
+
+impl<T: Serialize> Serialize for RenamedKeys<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = serde_json::to_value(self.value).map_err(S::Error::custom)?;
+        round_floats(rename_keys(value, self.keys), self.precision).serialize(serializer)
+    }
+}
+
+// A single input gives a report object, several give an array tagged by file
+pub fn to_json(
+    files: &[FileReport],
+    keys: &KeyMap,
+    precision: usize,
+) -> serde_json::Result<String> {
+    match files {
+        [file] => serde_json::to_string_pretty(&RenamedKeys {
+            value: &file.report,
+            keys,
+            precision,
+        }),
+        _ => {
+            let entries: Vec<FileEntry> = files
+                .iter()
+                .map(|file| FileEntry {
+                    file: file.name(),
+                    report: &file.report,
+                })
+                .collect();
+            serde_json::to_string_pretty(&RenamedKeys {
+                value: &entries,
+                keys,
+                precision,
+            })
+        }
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Same columns as the JSON keys, in the same order
+pub fn to_csv(files: &[FileReport], precision: usize) -> String {
+    let mut csv = json_fields().join(",");
+    csv.push('\n');
+    for file in files {
+        let mut row = vec![csv_field(&file.name())];
+        if let Ok(Value::Object(report)) = serde_json::to_value(&file.report) {
+            row.extend(report.values().map(|value| match value.as_f64() {
+                Some(float) if value.is_f64() => format_float(float, precision),
+                _ => csv_field(&value.to_string()),
+            }));
+        }
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+// ********* Columns **********
+
+// Output of --columns: only the selected metrics, in selection order
+
+pub fn columns_to_csv(files: &[FileReport], names: &[String], precision: usize) -> String {
+    let mut csv = format!("file,{}\n", names.join(","));
+    for file in files {
+        let mut row = vec![csv_field(&file.name())];
+        row.extend(file.columns.iter().map(|value| value.format(precision)));
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn column_object(file: &FileReport, names: &[String], precision: usize) -> Value {
+    let object: Map<String, Value> = names
+        .iter()
+        .cloned()
+        .zip(file.columns.iter().map(|value| match value {
+            MetricValue::Count(count) => Value::from(*count),
+            MetricValue::Float(float) => round_floats(Value::from(*float), precision),
+        }))
+        .collect();
+    Value::Object(object)
+}
+
+// Shaped like `to_json`: an object for one input, an array tagged by file
+pub fn columns_to_json(
+    files: &[FileReport],
+    names: &[String],
+    precision: usize,
+) -> serde_json::Result<String> {
+    let value = match files {
+        [file] => column_object(file, names, precision),
+        _ => Value::Array(
+            files
+                .iter()
+                .map(|file| {
+                    let mut entry = Map::new();
+                    entry.insert("file".to_string(), Value::from(file.name()));
+                    if let Value::Object(columns) = column_object(file, names, precision) {
+                        entry.extend(columns);
+                    }
+                    Value::Object(entry)
+                })
+                .collect(),
+        ),
+    };
+    serde_json::to_string_pretty(&value)
+}
+
+pub fn columns_lines(files: &[FileReport], names: &[String], precision: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for file in files {
+        let indent = if let [_] = files {
+            ""
+        } else {
+            lines.push(file.path.display().to_string());
+            "  "
+        };
+        for (name, value) in names.iter().zip(&file.columns) {
+            lines.push(format!("{indent}{name}: {}", value.format(precision)));
+        }
+    }
+    lines
+}
+
+// ********* Metadata **********
+
+// Producer of the data, added by --with-metadata
+pub const GENERATOR: &str = env!("CARGO_PKG_NAME");
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// A report object gets `generator` and `version` as its first keys,
+// an array of reports is moved under `files` next to them
+pub fn with_metadata_json(json: &str) -> serde_json::Result<String> {
+    let mut object = Map::new();
+    object.insert("generator".to_string(), Value::from(GENERATOR));
+    object.insert("version".to_string(), Value::from(VERSION));
+    match serde_json::from_str(json)? {
+        Value::Object(report) => object.extend(report),
+        other => {
+            object.insert("files".to_string(), other);
+        }
+    }
+    serde_json::to_string_pretty(&Value::Object(object))
+}
+
+// Two more columns, repeated on every row so each one stays self-describing
+pub fn with_metadata_csv(csv: &str) -> String {
+    let mut lines = csv.lines();
+    let mut tagged = match lines.next() {
+        Some(header) => format!("{header},generator,version\n"),
+        None => return String::new(),
+    };
+    for line in lines {
+        tagged.push_str(&format!("{line},{GENERATOR},{VERSION}\n"));
+    }
+    tagged
+}
+
+// ********* Checksum **********
+
+// Hex SHA-256 of the report exactly as written, see --checksum
+pub fn checksum(report: &str) -> String {
+    format!("{:x}", Sha256::digest(report.as_bytes()))
+}
+
+// Printed last, covering everything printed before it
+pub fn checksum_line(report: &str) -> String {
+    format!("SHA-256: {}", checksum(report))
+}
+
+// Like the metadata, a `checksum` key on the report object, or next to
+// `files` for an array of reports. It covers the object printed without it.
+pub fn with_checksum_json(json: &str) -> serde_json::Result<String> {
+    let mut object = match serde_json::from_str(json)? {
+        Value::Object(report) => report,
+        other => Map::from_iter([("files".to_string(), other)]),
+    };
+    let hash = checksum(&serde_json::to_string_pretty(&object)?);
+    object.insert("checksum".to_string(), Value::from(hash));
+    serde_json::to_string_pretty(&Value::Object(object))
+}
+
+// A trailing comment, so the rows above stay plain CSV
+pub fn with_checksum_csv(csv: &str) -> String {
+    format!("{csv}# {}\n", checksum_line(csv))
+}
+
+// Next to `path`, in the `sha256sum` format so `sha256sum -c` can verify it
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".sha256");
+    PathBuf::from(sidecar)
+}
+
+pub fn sidecar_line(path: &Path, report: &str) -> String {
+    let name = path.file_name().unwrap_or(path.as_os_str());
+    format!("{}  {}\n", checksum(report), name.to_string_lossy())
+}
+
+// ********* Frequencies **********
+
+// A `word,count` header then one row per word, in the given order, see --freq-csv
+pub fn write_frequency_csv(path: &Path, frequencies: &[(String, usize)]) -> csv::Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["word", "count"])?;
+    for (word, count) in frequencies {
+        writer.write_record([word.as_str(), &count.to_string()])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// ********* Banner **********
+
+const BANNER_HEIGHT: usize = 5;
+
+fn glyph(c: char) -> [&'static str; BANNER_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        'A' => [" # ", "# #", "###", "# #", "# #"],
+        'B' => ["## ", "# #", "## ", "# #", "## "],
+        'C' => [" ##", "#  ", "#  ", "#  ", " ##"],
+        'D' => ["## ", "# #", "# #", "# #", "## "],
+        'E' => ["###", "#  ", "## ", "#  ", "###"],
+        'F' => ["###", "#  ", "## ", "#  ", "#  "],
+        'G' => [" ##", "#  ", "# #", "# #", " ##"],
+        'H' => ["# #", "# #", "###", "# #", "# #"],
+        'I' => ["###", " # ", " # ", " # ", "###"],
+        'J' => ["  #", "  #", "  #", "# #", " # "],
+        'K' => ["# #", "# #", "## ", "# #", "# #"],
+        'L' => ["#  ", "#  ", "#  ", "#  ", "###"],
+        'M' => ["#   #", "## ##", "# # #", "#   #", "#   #"],
+        'N' => ["#  #", "## #", "# ##", "#  #", "#  #"],
+        'O' => [" # ", "# #", "# #", "# #", " # "],
+        'P' => ["## ", "# #", "## ", "#  ", "#  "],
+        'Q' => [" # ", "# #", "# #", "## ", " ##"],
+        'R' => ["## ", "# #", "## ", "# #", "# #"],
+        'S' => [" ##", "#  ", " # ", "  #", "## "],
+        'T' => ["###", " # ", " # ", " # ", " # "],
+        'U' => ["# #", "# #", "# #", "# #", "###"],
+        'V' => ["# #", "# #", "# #", "# #", " # "],
+        'W' => ["#   #", "#   #", "# # #", "## ##", "#   #"],
+        'X' => ["# #", "# #", " # ", "# #", "# #"],
+        'Y' => ["# #", "# #", " # ", " # ", " # "],
+        'Z' => ["###", "  #", " # ", "#  ", "###"],
+        '0' => ["###", "# #", "# #", "# #", "###"],
+        '1' => [" # ", "## ", " # ", " # ", "###"],
+        '2' => ["## ", "  #", " # ", "#  ", "###"],
+        '3' => ["## ", "  #", " # ", "  #", "## "],
+        '4' => ["# #", "# #", "###", "  #", "  #"],
+        '5' => ["###", "#  ", "## ", "  #", "## "],
+        '6' => [" ##", "#  ", "###", "# #", "###"],
+        '7' => ["###", "  #", " # ", " # ", " # "],
+        '8' => ["###", "# #", "###", "# #", "###"],
+        '9' => ["###", "# #", "###", "  #", "## "],
+        '.' => [" ", " ", " ", " ", "#"],
+        '-' => ["   ", "   ", "###", "   ", "   "],
+        '_' => ["   ", "   ", "   ", "   ", "###"],
+        '/' => ["  #", "  #", " # ", "#  ", "#  "],
+        ' ' => ["  ", "  ", "  ", "  ", "  "],
+        _ => ["## ", "  #", " # ", "   ", " # "],
+    }
+}
+
+// Big letters spelling `text`, one string per row
+pub fn banner(text: &str) -> Vec<String> {
+    (0..BANNER_HEIGHT)
+        .map(|row| {
+            let glyphs: Vec<&str> = text.chars().map(|c| glyph(c)[row]).collect();
+            glyphs.join(" ").trim_end().to_string()
+        })
+        .collect()
+}
+
+// *********      Test     **********
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use text_analyzer::{FunctionResult, analyze};
+
+    fn file(path: &str, text: &str) -> FunctionResult<FileReport> {
+        Ok(FileReport {
+            path: PathBuf::from(path),
+            report: analyze(text)?,
+            extra: Vec::new(),
+            columns: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn test_banner() {
+        assert_eq!(
+            banner("Hi"),
+            vec!["# # ###", "# #  #", "###  #", "# #  #", "# # ###"]
+        );
+        assert!(banner("").iter().all(String::is_empty));
+    }
+
+    #[test]
+    fn test_to_json() -> FunctionResult<()> {
+        let single = to_json(
+            &[file("a.txt", "a b c")?],
+            &KeyMap::new(),
+            DEFAULT_PRECISION,
+        )?;
+        let value: serde_json::Value = serde_json::from_str(&single)?;
+        assert_eq!(value["word_count"], 3);
+
+        let multi = to_json(
+            &[file("a.txt", "a b")?, file("b.txt", "c")?],
+            &KeyMap::new(),
+            DEFAULT_PRECISION,
+        )?;
+        let value: serde_json::Value = serde_json::from_str(&multi)?;
+        assert_eq!(value[1]["file"], "b.txt");
+        assert_eq!(value[1]["word_count"], 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_json_renamed_keys() -> FunctionResult<()> {
+        let keys = KeyMap::from([("char_count".to_string(), "chars".to_string())]);
+        let json = to_json(&[file("a.txt", "a b c")?], &keys, DEFAULT_PRECISION)?;
+        assert!(json.contains("\"chars\": 5"));
+        assert!(!json.contains("char_count"));
+
+        // unmapped keys keep their name and every key its position
+        let value: serde_json::Value = serde_json::from_str(&json)?;
+        let object = value.as_object().ok_or("not an object")?;
+        let names: Vec<&String> = object.keys().collect();
+        assert_eq!(
+            names,
+            [
+                "chars",
+                "chars_no_spaces",
+                "word_count",
+                "line_count",
+                "unique_line_count",
+                "avg_words_per_line"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_precision() -> FunctionResult<()> {
+        assert_eq!(format_float(2.0, 3), "2.000");
+        assert_eq!(format_float(2.0, 0), "2");
+
+        let files = [file("a.txt", "a b c\nd e f g")?];
+        let json = to_json(&files, &KeyMap::new(), 1)?;
+        assert!(json.contains("\"avg_words_per_line\": 3.5"));
+        let json = to_json(&files, &KeyMap::new(), 0)?;
+        assert!(json.contains("\"avg_words_per_line\": 4.0"));
+        assert!(to_csv(&files, 3).ends_with(",3.500\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_columns() -> FunctionResult<()> {
+        let names = ["words".to_string(), "avg_words_per_line".to_string()];
+        let mut a = file("a.txt", "a b")?;
+        a.columns = vec![MetricValue::Count(2), MetricValue::Float(2.0)];
+        let mut b = file("b.txt", "c")?;
+        b.columns = vec![MetricValue::Count(1), MetricValue::Float(1.0)];
+
+        assert_eq!(
+            columns_to_csv(&[a.clone(), b.clone()], &names, 1),
+            "file,words,avg_words_per_line\na.txt,2,2.0\nb.txt,1,1.0\n"
+        );
+        let json: Value =
+            serde_json::from_str(&columns_to_json(std::slice::from_ref(&a), &names, 2)?)?;
+        assert_eq!(json["words"], 2);
+        assert_eq!(json.as_object().map(Map::len), Some(2));
+        assert_eq!(
+            columns_lines(&[a, b], &names, 0),
+            [
+                "a.txt",
+                "  words: 2",
+                "  avg_words_per_line: 2",
+                "b.txt",
+                "  words: 1",
+                "  avg_words_per_line: 1"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_fields() {
+        assert_eq!(
+            json_fields(),
+            [
+                "file",
+                "char_count",
+                "chars_no_spaces",
+                "word_count",
+                "line_count",
+                "unique_line_count",
+                "avg_words_per_line"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_checksum() -> FunctionResult<()> {
+        let report = to_json(&[file("a.txt", "lorem ipsum")?], &KeyMap::new(), 2)?;
+        let hash = checksum(&report);
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, format!("{:x}", Sha256::digest(report.as_bytes())));
+        assert_ne!(hash, checksum(&report.replace('2', "3")));
+
+        let path = Path::new("out/report.json");
+        assert_eq!(sidecar_path(path), Path::new("out/report.json.sha256"));
+        assert_eq!(
+            sidecar_line(path, &report),
+            format!("{hash}  report.json\n")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_checksum_in_output() -> FunctionResult<()> {
+        let report = to_json(&[file("a.txt", "lorem ipsum")?], &KeyMap::new(), 2)?;
+        let mut json: Value = serde_json::from_str(&with_checksum_json(&report)?)?;
+        let hash = json
+            .as_object_mut()
+            .and_then(|object| object.remove("checksum"));
+        assert_eq!(hash, Some(Value::from(checksum(&report))));
+        assert_eq!(serde_json::to_string_pretty(&json)?, report);
+
+        let several = r#"[{"file": "a"}]"#;
+        let json: Value = serde_json::from_str(&with_checksum_json(several)?)?;
+        assert_eq!(json["files"][0]["file"], "a");
+        assert!(json["checksum"].is_string());
+
+        let csv = with_checksum_csv("file,word_count\na,2\n");
+        let (rows, last) = csv.trim_end().rsplit_once('\n').unwrap_or_default();
+        assert_eq!(
+            last,
+            format!("# SHA-256: {}", checksum("file,word_count\na,2\n"))
+        );
+        assert_eq!(format!("{rows}\n"), "file,word_count\na,2\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_metadata() -> FunctionResult<()> {
+        let single: Value = serde_json::from_str(&with_metadata_json(r#"{"word_count": 2}"#)?)?;
+        assert_eq!(single["generator"], "text-analyzer");
+        assert_eq!(single["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(single["word_count"], 2);
+
+        let several: Value = serde_json::from_str(&with_metadata_json(r#"[{"file": "a"}]"#)?)?;
+        assert_eq!(several["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(several["files"][0]["file"], "a");
+
+        let csv = with_metadata_csv("file,word_count\na,2\n");
+        let version = env!("CARGO_PKG_VERSION");
+        assert_eq!(
+            csv,
+            format!("file,word_count,generator,version\na,2,text-analyzer,{version}\n")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_csv() -> FunctionResult<()> {
+        let csv = to_csv(
+            &[file("a,b.txt", "a b")?, file("-", "c")?],
+            DEFAULT_PRECISION,
+        );
+        assert_eq!(
+            csv,
+            "file,char_count,chars_no_spaces,word_count,line_count,unique_line_count,avg_words_per_line\n\"a,b.txt\",3,2,2,1,1,2.00\nstdin,1,1,1,1,1,1.00\n"
+        );
+        Ok(())
+    }
+}
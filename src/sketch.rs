@@ -0,0 +1,149 @@
+/*!
+ * Approximate word frequencies in bounded memory, for `--approx-freq`.
+ *
+ * Notes:
+ *  - a count-min sketch of `depth` rows of `width` counters estimates every count
+ *  - estimates never undercount; with `width = ⌈e / ε⌉` and `depth = ⌈ln(1 / δ)⌉`
+ *    they overcount by at most `ε × total words` with probability `1 - δ`
+ *  - only the `k` current best candidates are kept as strings, so memory does not
+ *    grow with the vocabulary
+ */
+
+use crate::{normalize_word, tokens};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+// ε = 0.1 %, δ = 1 %: ~2.7k counters per row, 5 rows
+pub const DEFAULT_EPSILON: f64 = 0.001;
+pub const DEFAULT_DELTA: f64 = 0.01;
+
+pub struct CountMinSketch {
+    width: usize,
+    rows: Vec<Vec<usize>>,
+}
+
+impl CountMinSketch {
+    // Sized for an overcount of at most `epsilon × total` with probability `1 - delta`
+    pub fn new(epsilon: f64, delta: f64) -> Self {
+        let width = (std::f64::consts::E / epsilon).ceil().max(1.0) as usize;
+        let depth = (1.0 / delta).ln().ceil().max(1.0) as usize;
+        CountMinSketch {
+            width,
+            rows: vec![vec![0; width]; depth],
+        }
+    }
+
+    // One independent hash per row, obtained by seeding with the row number
+    fn slot(&self, row: usize, word: &str) -> usize {
+        let mut hasher = DefaultHasher::new();
+        row.hash(&mut hasher);
+        word.hash(&mut hasher);
+        (hasher.finish() % self.width as u64) as usize
+    }
+
+    pub fn add(&mut self, word: &str) {
+        for row in 0..self.rows.len() {
+            let slot = self.slot(row, word);
+            self.rows[row][slot] += 1;
+        }
+    }
+
+    pub fn estimate(&self, word: &str) -> usize {
+        (0..self.rows.len())
+            .map(|row| self.rows[row][self.slot(row, word)])
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+// The `k` most frequent words seen so far, by sketch estimate
+pub struct TopWords {
+    sketch: CountMinSketch,
+    k: usize,
+    ignore_case: bool,
+    candidates: Vec<(String, usize)>,
+}
+
+impl TopWords {
+    pub fn new(k: usize, ignore_case: bool) -> Self {
+        TopWords {
+            sketch: CountMinSketch::new(DEFAULT_EPSILON, DEFAULT_DELTA),
+            k,
+            ignore_case,
+            candidates: Vec::with_capacity(k + 1),
+        }
+    }
+
+    // Text can be fed in chunks, as long as no word is split between two of them
+    pub fn feed(&mut self, text: &str) {
+        for word in tokens(text).filter_map(|word| normalize_word(word, self.ignore_case)) {
+            self.sketch.add(&word);
+            let estimate = self.sketch.estimate(&word);
+            if let Some(candidate) = self.candidates.iter_mut().find(|(w, _)| *w == word) {
+                candidate.1 = estimate;
+            } else {
+                self.candidates.push((word, estimate));
+                if self.candidates.len() > self.k {
+                    let weakest = (0..self.candidates.len())
+                        .min_by_key(|&i| self.candidates[i].1)
+                        .unwrap_or(0);
+                    self.candidates.swap_remove(weakest);
+                }
+            }
+        }
+    }
+
+    // Most frequent first, alphabetically on ties
+    pub fn top(&self) -> Vec<(String, usize)> {
+        let mut top = self.candidates.clone();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top
+    }
+}
+
+// *********      Test     **********
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::word_frequencies;
+
+    #[test]
+    fn test_sketch_never_undercounts() {
+        let text = "the cat and the dog and the bird saw the cat";
+        let mut sketch = CountMinSketch::new(DEFAULT_EPSILON, DEFAULT_DELTA);
+        for word in tokens(text) {
+            sketch.add(word);
+        }
+        let total = tokens(text).count() as f64;
+        for (word, exact) in word_frequencies(text, false) {
+            let estimate = sketch.estimate(&word);
+            assert!(estimate >= exact);
+            assert!(estimate as f64 <= exact as f64 + DEFAULT_EPSILON * total + 1.0);
+        }
+        assert_eq!(sketch.estimate("absent"), 0);
+    }
+
+    #[test]
+    fn test_top_words_match_exact() {
+        let text = "b a c a b a d a c b e";
+        let mut top = TopWords::new(3, false);
+        // fed in two chunks, as a stream would be
+        top.feed("b a c a b a");
+        top.feed(" d a c b e");
+        assert_eq!(
+            top.top(),
+            [
+                ("a".to_string(), 4),
+                ("b".to_string(), 3),
+                ("c".to_string(), 2)
+            ]
+        );
+        assert_eq!(word_frequencies(text, false)["a"], 4);
+
+        let mut folded = TopWords::new(1, true);
+        folded.feed("Word word WORD other");
+        assert_eq!(folded.top(), [("word".to_string(), 3)]);
+    }
+}
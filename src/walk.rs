@@ -0,0 +1,187 @@
+/*!
+ * Expansion of directory inputs into the files they contain.
+ *
+ * Notes:
+ *  - directories are walked recursively, in sorted order
+ *  - glob filters only apply to discovered files, not to files named explicitly
+ *  - depth 0 is the files directly in a given directory, 1 adds their subdirectories, ...
+ */
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Default)]
+pub struct PathFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+    max_depth: Option<usize>,
+}
+
+fn glob_set(patterns: &[String]) -> Result<Option<GlobSet>, globset::Error> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern)?);
+    }
+    Ok(Some(builder.build()?))
+}
+
+impl PathFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, globset::Error> {
+        Ok(PathFilter {
+            include: glob_set(include)?,
+            exclude: glob_set(exclude)?,
+            max_depth: None,
+        })
+    }
+
+    // No limit by default
+    pub fn with_max_depth(self, max_depth: Option<usize>) -> Self {
+        PathFilter { max_depth, ..self }
+    }
+
+    // `path` is relative to the walked directory; exclude wins over include
+    pub fn accepts(&self, path: &Path) -> bool {
+        if self.exclude.as_ref().is_some_and(|set| set.is_match(path)) {
+            return false;
+        }
+        self.include.as_ref().is_none_or(|set| set.is_match(path))
+    }
+}
+
+fn walk(
+    dir: &Path,
+    root: &Path,
+    depth: usize,
+    filter: &PathFilter,
+    files: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    let mut entries: Vec<fs::DirEntry> = fs::read_dir(dir)?.collect::<io::Result<_>>()?;
+    entries.sort_by_key(|entry| entry.path());
+
+    for entry in entries {
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if filter.max_depth.is_none_or(|max| depth < max) {
+                walk(&path, root, depth + 1, filter, files)?;
+            }
+        } else if file_type.is_file() {
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            if filter.accepts(relative) {
+                files.push(path);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Files are kept as given, directories are replaced by the files below them
+pub fn expand_inputs(paths: &[PathBuf], filter: &PathFilter) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            walk(path, path, 0, filter, &mut files)?;
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+// *********      Test     **********
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use text_analyzer::FunctionResult;
+
+    fn tree() -> FunctionResult<tempfile::TempDir> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir_all(dir.path().join("docs/drafts"))?;
+        for name in [
+            "a.txt",
+            "b.tmp",
+            "docs/c.md",
+            "docs/drafts/d.tmp",
+            "docs/drafts/e.md",
+        ] {
+            fs::write(dir.path().join(name), "lorem")?;
+        }
+        Ok(dir)
+    }
+
+    fn relative(dir: &Path, files: Vec<PathBuf>) -> FunctionResult<Vec<String>> {
+        files
+            .iter()
+            .map(|file| Ok(file.strip_prefix(dir)?.display().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_expand_inputs() -> FunctionResult<()> {
+        let dir = tree()?;
+        let files = expand_inputs(&[dir.path().to_path_buf()], &PathFilter::default())?;
+        assert_eq!(
+            relative(dir.path(), files)?,
+            [
+                "a.txt",
+                "b.tmp",
+                "docs/c.md",
+                "docs/drafts/d.tmp",
+                "docs/drafts/e.md"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_exclude_glob() -> FunctionResult<()> {
+        let dir = tree()?;
+        let filter = PathFilter::new(&[], &["**/*.tmp".to_string()])?;
+        let files = expand_inputs(&[dir.path().to_path_buf()], &filter)?;
+        assert_eq!(
+            relative(dir.path(), files)?,
+            ["a.txt", "docs/c.md", "docs/drafts/e.md"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_depth() -> FunctionResult<()> {
+        let dir = tree()?;
+        let walk = |depth| {
+            let filter = PathFilter::default().with_max_depth(Some(depth));
+            expand_inputs(&[dir.path().to_path_buf()], &filter)
+        };
+
+        assert_eq!(relative(dir.path(), walk(0)?)?, ["a.txt", "b.tmp"]);
+        assert_eq!(
+            relative(dir.path(), walk(1)?)?,
+            ["a.txt", "b.tmp", "docs/c.md"]
+        );
+        assert_eq!(walk(2)?.len(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_include_and_exclude_globs() -> FunctionResult<()> {
+        let dir = tree()?;
+        let include = ["**/*.md".to_string(), "*.txt".to_string()];
+        let filter = PathFilter::new(&include, &["docs/drafts/**".to_string()])?;
+        let files = expand_inputs(&[dir.path().to_path_buf()], &filter)?;
+        assert_eq!(relative(dir.path(), files)?, ["a.txt", "docs/c.md"]);
+
+        // explicitly named files are never filtered
+        let named = dir.path().join("b.tmp");
+        assert_eq!(
+            expand_inputs(std::slice::from_ref(&named), &filter)?,
+            [named]
+        );
+        Ok(())
+    }
+}
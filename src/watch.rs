@@ -0,0 +1,103 @@
+/*!
+ * Watch mode: the report is redrawn when the watched files change.
+ *
+ * Notes:
+ *  - changes are detected by polling modification times
+ *  - redraws are coalesced, at most one per refresh interval
+ */
+
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
+
+pub const DEFAULT_REFRESH_MS: u64 = 250;
+
+// Change events arriving within `interval` of the last redraw are merged
+// into a single redraw once the interval has passed
+pub struct RedrawTimer {
+    interval: Duration,
+    last_draw: Option<Instant>,
+    pending: bool,
+}
+
+impl RedrawTimer {
+    // The first poll always draws
+    pub fn new(interval: Duration) -> Self {
+        RedrawTimer {
+            interval,
+            last_draw: None,
+            pending: true,
+        }
+    }
+
+    pub fn event(&mut self) {
+        self.pending = true;
+    }
+
+    // Whether to redraw at `now`; a `true` counts as the redraw
+    pub fn poll(&mut self, now: Instant) -> bool {
+        if !self.pending {
+            return false;
+        }
+        if let Some(last) = self.last_draw
+            && now.duration_since(last) < self.interval
+        {
+            return false;
+        }
+        self.pending = false;
+        self.last_draw = Some(now);
+        true
+    }
+}
+
+// Compared between polls, missing files are `None`
+pub fn modified_times(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| fs::metadata(file).and_then(|meta| meta.modified()).ok())
+        .collect()
+}
+
+// *********      Test     **********
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use text_analyzer::FunctionResult;
+
+    #[test]
+    fn test_redraw_timer_coalesces() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut timer = RedrawTimer::new(Duration::from_millis(250));
+
+        assert!(timer.poll(at(0)));
+        assert!(!timer.poll(at(5)));
+
+        // a burst of events inside the interval gives one redraw at its end
+        for ms in [10, 20, 30, 100] {
+            timer.event();
+            assert!(!timer.poll(at(ms)));
+        }
+        assert!(timer.poll(at(250)));
+        assert!(!timer.poll(at(600)));
+
+        // an event after a quiet period is drawn right away
+        timer.event();
+        assert!(timer.poll(at(700)));
+    }
+
+    #[test]
+    fn test_modified_times() -> FunctionResult<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("a.txt");
+        let missing = dir.path().join("missing.txt");
+        fs::write(&file, "lorem")?;
+
+        let times = modified_times(&[file, missing]);
+        assert!(times[0].is_some());
+        assert!(times[1].is_none());
+        Ok(())
+    }
+}